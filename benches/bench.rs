@@ -4,8 +4,9 @@ extern crate test;
 
 #[cfg(test)]
 mod bench {
-    use test::Bencher;
     use bytestrie::DictionaryIterator;
+    use bytestrie::{Dictionary, Segmenter};
+    use test::Bencher;
 
     const TEST_KM_STR: &str = "ភាសាខ្មែរភាសាខ្មែរភាសាខ្មែរ";
     const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
@@ -15,4 +16,18 @@ mod bench {
         let utf16: Vec<u16> = TEST_KM_STR.encode_utf16().map(|x| x).collect();
         b.iter(|| DictionaryIterator::new(KHMER_DATA, &utf16).count())
     }
+
+    #[bench]
+    fn segment_batch_utf16(b: &mut Bencher) {
+        let utf16: Vec<u16> = TEST_KM_STR.encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        let mut boundaries = Vec::new();
+        let mut ranges = Vec::new();
+        b.iter(|| {
+            boundaries.clear();
+            ranges.clear();
+            segmenter.segment_batch((0..100).map(|_| &utf16[..]), &mut boundaries, &mut ranges);
+            boundaries.len()
+        })
+    }
 }
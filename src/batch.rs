@@ -0,0 +1,59 @@
+use crate::segmenter::*;
+use core::ops::Range;
+
+impl<'a> Segmenter<'a> {
+    /// Segments each of `inputs`, appending the end offsets of its segments
+    /// to `boundaries` and the range of `boundaries` that holds them to
+    /// `ranges`. Offsets are relative to the start of each input.
+    ///
+    /// The working buffers of the strategy are shared by all inputs, and
+    /// reusing `boundaries` and `ranges` across batches, after clearing
+    /// them, avoids allocating per input. See `segment_into`.
+    pub fn segment_batch<'i>(
+        &self,
+        inputs: impl IntoIterator<Item = &'i [u16]>,
+        boundaries: &mut Vec<usize>,
+        ranges: &mut Vec<Range<usize>>,
+    ) {
+        let mut scratch = Scratch::default();
+        for input in inputs {
+            let start = boundaries.len();
+            self.segment_into_with(input, boundaries, &mut scratch);
+            ranges.push(start..boundaries.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::*;
+    use crate::dictionary::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn batch_test() {
+        let texts = ["ខ្ញុំស្រឡាញ់អ្នក", "", "សួស្តីពិភពលោក", "abc ភាសាខ្មែរ"];
+        let inputs: Vec<Vec<u16>> = texts.iter().map(|t| t.encode_utf16().collect()).collect();
+        let mut boundaries = Vec::new();
+        let mut ranges = Vec::new();
+        for strategy in [Strategy::Forward, Strategy::Khmer] {
+            let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA)).with_strategy(strategy);
+            boundaries.clear();
+            ranges.clear();
+            segmenter.segment_batch(inputs.iter().map(|i| &i[..]), &mut boundaries, &mut ranges);
+            assert_eq!(ranges.len(), inputs.len());
+            for (input, range) in inputs.iter().zip(ranges.iter()) {
+                assert_eq!(boundaries[range.clone()], segmenter.segment(input)[..]);
+            }
+        }
+        assert_eq!(ranges[1], 3..3);
+
+        // Existing contents are kept.
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let mut boundaries = vec![100];
+        let utf16: Vec<u16> = "焼肉定食".encode_utf16().collect();
+        segmenter.segment_into(&utf16, &mut boundaries);
+        assert_eq!(boundaries, [100, 2, 4]);
+    }
+}
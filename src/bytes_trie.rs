@@ -50,6 +50,25 @@ fn skip_value(pos: usize, lead_byte: u8) -> usize {
     }
 }
 
+fn read_value(trie_data: &[u8], pos: usize, lead_byte: u8) -> i32 {
+    if lead_byte < MIN_TWO_BYTE_VALUE_LEAD {
+        (lead_byte - MIN_ONE_BYTE_VALUE_LEAD) as i32
+    } else if lead_byte < MIN_THREE_BYTE_VALUE_LEAD {
+        (((lead_byte - MIN_TWO_BYTE_VALUE_LEAD) as i32) << 8) | trie_data[pos] as i32
+    } else if lead_byte < FOUR_BYTE_VALUE_LEAD {
+        (((lead_byte - MIN_THREE_BYTE_VALUE_LEAD) as i32) << 16)
+            | (trie_data[pos] as i32) << 8
+            | trie_data[pos + 1] as i32
+    } else if lead_byte == FOUR_BYTE_VALUE_LEAD {
+        (trie_data[pos] as i32) << 16 | (trie_data[pos + 1] as i32) << 8 | trie_data[pos + 2] as i32
+    } else {
+        (trie_data[pos] as i32) << 24
+            | (trie_data[pos + 1] as i32) << 16
+            | (trie_data[pos + 2] as i32) << 8
+            | trie_data[pos + 3] as i32
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct BytesTrie {
     pos_: Option<usize>,
@@ -61,24 +80,26 @@ impl Trie for BytesTrie {
     // Traverses the trie from the initial state for this input char.
     // Equivalent to reset() then next(inUnit)
     fn first(&mut self, trie_data: &[u8], c: i32) -> TrieResult {
-        let mut in_byte = c;
         self.remaining_match_length_ = None;
-        if in_byte < 0 {
-            in_byte += 0x100;
+        // Units outside the byte range (e.g. characters that the dictionary
+        // transform cannot map) never match.
+        if !(0..=0xff).contains(&c) {
+            self.stop();
+            return TrieResult::NoMatch;
         }
-        self.next_impl(trie_data, self.root_, in_byte as u8)
+        self.next_impl(trie_data, self.root_, c as u8)
     }
 
     // Traverses the trie from the current state for this input char.
     fn next(&mut self, trie_data: &[u8], c: i32) -> TrieResult {
-        let mut in_byte = c;
         if self.pos_.is_none() {
             return TrieResult::NoMatch;
         }
-        if in_byte < 0 {
-            in_byte += 0x100;
+        if !(0..=0xff).contains(&c) {
+            self.stop();
+            return TrieResult::NoMatch;
         }
-        let in_byte = in_byte as u8;
+        let in_byte = c as u8;
         let mut pos = self.pos_.unwrap();
         if let Some(length) = self.remaining_match_length_ {
             // Remaining part of a linear-match node
@@ -92,7 +113,7 @@ impl Trie for BytesTrie {
                         return BytesTrie::value_result(node);
                     }
                 } else {
-                    self.remaining_match_length_ = Some(length);
+                    self.remaining_match_length_ = Some(length - 1);
                 }
                 return TrieResult::NoValue;
             }
@@ -103,12 +124,19 @@ impl Trie for BytesTrie {
         }
     }
 
-    fn box_clone(&self) -> Box<dyn Trie> {
-        Box::new(BytesTrie {
-            pos_: self.pos_,
-            root_: self.root_,
-            remaining_match_length_: self.remaining_match_length_,
-        })
+    // Returns the value for the string matched so far.
+    // Only valid after first()/next() returned TrieResult::Intermediate or TrieResult::FinalValue.
+    fn value(&self, trie_data: &[u8]) -> i32 {
+        let pos = self.pos_.unwrap();
+        let lead_byte = trie_data[pos];
+        assert!(lead_byte >= MIN_VALUE_LEAD);
+        read_value(trie_data, pos + 1, lead_byte >> 1)
+    }
+
+    fn entries(&self, trie_data: &[u8]) -> Vec<(Vec<u16>, i32)> {
+        let mut entries = Vec::new();
+        self.collect_node(trie_data, self.root_, &mut Vec::new(), &mut entries);
+        entries
     }
 }
 
@@ -256,6 +284,81 @@ impl BytesTrie {
         TrieResult::NoMatch
     }
 
+    fn collect_node(
+        &self,
+        trie_data: &[u8],
+        pos: usize,
+        prefix: &mut Vec<u16>,
+        entries: &mut Vec<(Vec<u16>, i32)>,
+    ) {
+        let prefix_length = prefix.len();
+        let mut pos = pos;
+        loop {
+            let node = trie_data[pos];
+            pos += 1;
+            if node < MIN_LINEAR_MATCH {
+                let mut length = node as usize;
+                if length == 0 {
+                    length = trie_data[pos] as usize;
+                    pos += 1;
+                }
+                self.collect_branch(trie_data, pos, length + 1, prefix, entries);
+                break;
+            } else if node < MIN_VALUE_LEAD {
+                let length = (node - MIN_LINEAR_MATCH) as usize + 1;
+                prefix.extend(trie_data[pos..pos + length].iter().map(|b| *b as u16));
+                pos += length;
+            } else {
+                entries.push((prefix.clone(), read_value(trie_data, pos, node >> 1)));
+                if node & VALUE_IS_FINAL != 0 {
+                    break;
+                }
+                pos = skip_value(pos, node);
+            }
+        }
+        prefix.truncate(prefix_length);
+    }
+
+    fn collect_branch(
+        &self,
+        trie_data: &[u8],
+        pos: usize,
+        length: usize,
+        prefix: &mut Vec<u16>,
+        entries: &mut Vec<(Vec<u16>, i32)>,
+    ) {
+        let mut pos = pos;
+        let mut length = length;
+        while length > MAX_BRANCH_LINEAR_SUB_NODE_LENGTH {
+            // The units less than the split unit are at the jump target.
+            let less_than = self.jump_by_delta(trie_data, pos + 1);
+            self.collect_branch(trie_data, less_than, length >> 1, prefix, entries);
+            length -= length >> 1;
+            pos = self.skip_delta(trie_data, pos + 1);
+        }
+        loop {
+            prefix.push(trie_data[pos] as u16);
+            pos += 1;
+            let node = trie_data[pos];
+            let value = read_value(trie_data, pos + 1, node >> 1);
+            pos = skip_value(pos + 1, node);
+            if node & VALUE_IS_FINAL != 0 {
+                entries.push((prefix.clone(), value));
+            } else {
+                // The non-final value is the jump delta.
+                self.collect_node(trie_data, pos + value as usize, prefix, entries);
+            }
+            prefix.pop();
+            length -= 1;
+            if length <= 1 {
+                break;
+            }
+        }
+        prefix.push(trie_data[pos] as u16);
+        self.collect_node(trie_data, pos + 1, prefix, entries);
+        prefix.pop();
+    }
+
     fn stop(&mut self) {
         self.pos_ = None;
     }
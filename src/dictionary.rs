@@ -0,0 +1,374 @@
+use crate::bytes_trie::*;
+use crate::error::Error;
+use crate::script::*;
+use crate::trie::*;
+use crate::uchars_trie::*;
+
+// Indexes into the int32 array that follows the ICU data header.
+const IX_STRING_TRIE_OFFSET: usize = 0;
+const IX_TOTAL_SIZE: usize = 3;
+const IX_TRIE_TYPE: usize = 4;
+const IX_TRANSFORM: usize = 5;
+const IX_COUNT: usize = 8;
+
+pub(crate) const TRIE_TYPE_BYTES: u32 = 0;
+pub(crate) const TRIE_TYPE_UCHARS: u32 = 1;
+const TRIE_TYPE_MASK: u32 = 7;
+pub(crate) const TRIE_HAS_VALUES: u32 = 8;
+
+pub(crate) const TRANSFORM_TYPE_OFFSET: u32 = 0x1000000;
+const TRANSFORM_TYPE_MASK: u32 = 0x7f000000;
+const TRANSFORM_OFFSET_MASK: u32 = 0x1fffff;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+// Maps a character to a trie unit, or -1 if the dictionary can't contain it.
+pub(crate) fn transform_unit(transform: u32, c: u16) -> i32 {
+    if transform & TRANSFORM_TYPE_MASK == TRANSFORM_TYPE_OFFSET {
+        match c {
+            0x200c => 0xfe,
+            0x200d => 0xff,
+            _ => {
+                let delta = c as i32 - (transform & TRANSFORM_OFFSET_MASK) as i32;
+                if (0..=0xfd).contains(&delta) {
+                    delta
+                } else {
+                    -1
+                }
+            }
+        }
+    } else {
+        c as i32
+    }
+}
+
+/// A dictionary in ICU's binary `.dict` format.
+#[derive(Clone, Copy)]
+pub struct Dictionary<'a> {
+    data: &'a [u8],
+    trie_offset: usize,
+    trie_type: u32,
+    has_values: bool,
+    transform: u32,
+    ignorable: &'a [char],
+}
+
+impl<'a> Dictionary<'a> {
+    /// Reads a dictionary. Panics if `data` is not a valid dictionary, see
+    /// `try_new`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::try_new(data).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Reads a dictionary, checking its ICU data header, its indexes and
+    /// that the trie lies inside `data`. The trie itself is not checked.
+    pub fn try_new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < 16 || data[2..4] != [0xda, 0x27] || &data[12..16] != b"Dict" {
+            return Err(Error::InvalidDictionary("not an ICU dictionary"));
+        }
+        // The ICU data header starts with its own size.
+        let header_size = u16::from_le_bytes([data[0], data[1]]) as usize;
+        if data.len() < header_size + IX_COUNT * 4 {
+            return Err(Error::InvalidDictionary("too small data"));
+        }
+        let index = |i: usize| read_u32(data, header_size + i * 4) as usize;
+        let trie_offset = index(IX_STRING_TRIE_OFFSET);
+        let total_size = index(IX_TOTAL_SIZE);
+        if total_size > data.len() - header_size
+            || trie_offset < IX_COUNT * 4
+            || trie_offset >= total_size
+        {
+            return Err(Error::InvalidDictionary("trie out of range"));
+        }
+        let trie_type = index(IX_TRIE_TYPE) as u32;
+        match trie_type & TRIE_TYPE_MASK {
+            TRIE_TYPE_BYTES => {}
+            TRIE_TYPE_UCHARS if (header_size + trie_offset) & 1 == 0 => {}
+            TRIE_TYPE_UCHARS => return Err(Error::InvalidDictionary("unaligned trie")),
+            _ => return Err(Error::InvalidDictionary("unknown type")),
+        }
+        let transform = index(IX_TRANSFORM) as u32;
+        if transform != 0 && transform & TRANSFORM_TYPE_MASK != TRANSFORM_TYPE_OFFSET {
+            return Err(Error::InvalidDictionary("unknown transform"));
+        }
+        Ok(Self {
+            data: &data[..header_size + total_size],
+            trie_offset: header_size + trie_offset,
+            trie_type: trie_type & TRIE_TYPE_MASK,
+            has_values: trie_type & TRIE_HAS_VALUES != 0,
+            transform,
+            ignorable: &[],
+        })
+    }
+
+    /// Skips the characters in `ignorable`, such as U+200B ZERO WIDTH SPACE,
+    /// U+00AD SOFT HYPHEN or variation selectors, when they appear inside or
+    /// right after a word. They are part of the word's span but not of the
+    /// match. A word never starts with one.
+    pub fn with_ignorable(mut self, ignorable: &'a [char]) -> Self {
+        self.ignorable = ignorable;
+        self
+    }
+
+    /// Returns the script of the dictionary's words: the script of the
+    /// characters a bytes trie's transform maps, and `Script::Cj` for a
+    /// UChars trie, which ICU only uses for `cjdict.dict`.
+    pub fn script(&self) -> Option<Script> {
+        if self.transform & TRANSFORM_TYPE_MASK != TRANSFORM_TYPE_OFFSET {
+            return Some(Script::Cj);
+        }
+        let offset = self.transform & TRANSFORM_OFFSET_MASK;
+        (offset..offset + 0xfe)
+            .filter_map(char::from_u32)
+            .find_map(dictionary_script)
+    }
+
+    /// Returns whether the dictionary stores a value (e.g. a cost) per word.
+    pub fn has_values(&self) -> bool {
+        self.has_values
+    }
+
+    /// Returns the value stored for `word`, or `None` if it is not a word of
+    /// this dictionary.
+    pub fn value(&self, word: &[u16]) -> Result<Option<i32>, Error> {
+        if !self.has_values {
+            return Err(Error::NoValues);
+        }
+        let mut trie = self.trie();
+        let mut result = TrieResult::NoMatch;
+        let mut pos = 0;
+        while pos < word.len() {
+            if pos > 0 {
+                let next = self.skip_ignorable(word, pos, word.len());
+                if next > pos {
+                    pos = next;
+                    continue;
+                }
+            }
+            result = match pos {
+                0 => trie.first(self.data, self.transform(word[pos])),
+                _ => trie.next(self.data, self.transform(word[pos])),
+            };
+            if result == TrieResult::NoMatch {
+                return Ok(None);
+            }
+            pos += 1;
+        }
+        match result {
+            TrieResult::FinalValue | TrieResult::Intermediate => Ok(Some(trie.value(self.data))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns all words of the dictionary with their values, in the order
+    /// of the trie. Words of a dictionary without values have the value 0.
+    pub fn entries(&self) -> Vec<(Vec<u16>, i32)> {
+        let mut entries = self.trie().entries(self.data);
+        if self.trie_type == TRIE_TYPE_BYTES {
+            for (word, _) in entries.iter_mut() {
+                for unit in word.iter_mut() {
+                    *unit = self.untransform(*unit as u8);
+                }
+            }
+        }
+        entries
+    }
+
+    pub(crate) fn trie_type(&self) -> u32 {
+        self.trie_type
+    }
+
+    pub(crate) fn transform_constant(&self) -> u32 {
+        self.transform
+    }
+
+    pub(crate) fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub(crate) fn trie(&self) -> TrieCursor {
+        match self.trie_type {
+            TRIE_TYPE_BYTES => TrieCursor::Bytes(BytesTrie::new(self.trie_offset)),
+            _ => TrieCursor::UChars(UCharsTrie::new(self.trie_offset)),
+        }
+    }
+
+    // Returns whether `c` can be part of a word, i.e. whether the transform
+    // maps it or it is a unit of an ignorable character.
+    pub(crate) fn contains_unit(&self, c: u16) -> bool {
+        self.transform(c) >= 0
+            || self
+                .ignorable
+                .iter()
+                .any(|i| i.encode_utf16(&mut [0; 2]).contains(&c))
+    }
+
+    // Returns the characters skipped when matching words.
+    pub(crate) fn ignorable(&self) -> &'a [char] {
+        self.ignorable
+    }
+
+    // Returns whether `c` is skipped when matching words.
+    pub(crate) fn is_ignorable(&self, c: char) -> bool {
+        self.ignorable.contains(&c)
+    }
+
+    // Returns the number of units of the ignorable character starting with
+    // `c` and followed by `next`, or 0. A surrogate pair may come in either
+    // order, so that reversed text works too.
+    pub(crate) fn ignorable_length(&self, c: u16, next: Option<u16>) -> usize {
+        if self.ignorable.is_empty() {
+            return 0;
+        }
+        let (units, length) = match next {
+            Some(next) if (0xd800..0xdc00).contains(&c) && (0xdc00..0xe000).contains(&next) => {
+                ([c, next], 2)
+            }
+            Some(next) if (0xdc00..0xe000).contains(&c) && (0xd800..0xdc00).contains(&next) => {
+                ([next, c], 2)
+            }
+            _ => ([c, 0], 1),
+        };
+        match core::char::decode_utf16(units[..length].iter().cloned()).next() {
+            Some(Ok(c)) if self.ignorable.contains(&c) => length,
+            _ => 0,
+        }
+    }
+
+    // Returns the offset after the ignorable characters at `pos`, but not
+    // past `end`.
+    pub(crate) fn skip_ignorable(&self, input: &[u16], mut pos: usize, end: usize) -> usize {
+        while pos < end {
+            let next = match input[pos] {
+                0xd800..=0xdbff => input[..end].get(pos + 1).cloned(),
+                _ => None,
+            };
+            match self.ignorable_length(input[pos], next) {
+                0 => break,
+                length => pos += length,
+            }
+        }
+        pos
+    }
+
+    fn untransform(&self, b: u8) -> u16 {
+        if self.transform & TRANSFORM_TYPE_MASK == TRANSFORM_TYPE_OFFSET {
+            match b {
+                0xfe => 0x200c,
+                0xff => 0x200d,
+                _ => (b as u32 + (self.transform & TRANSFORM_OFFSET_MASK)) as u16,
+            }
+        } else {
+            b as u16
+        }
+    }
+
+    pub(crate) fn transform(&self, c: u16) -> i32 {
+        transform_unit(self.transform, c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::dictionary_builder::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn has_values_test() {
+        assert!(!Dictionary::new(KHMER_DATA).has_values());
+        assert!(Dictionary::new(CJ_DATA).has_values());
+    }
+
+    #[test]
+    fn script_test() {
+        assert_eq!(Dictionary::new(KHMER_DATA).script(), Some(Script::Khmer));
+        assert_eq!(Dictionary::new(CJ_DATA).script(), Some(Script::Cj));
+        let data = build_words(DictionaryBuilder::bytes(0x0e80), &["ກ"]);
+        assert_eq!(Dictionary::new(&data).script(), Some(Script::Lao));
+    }
+
+    #[test]
+    fn value_test() {
+        let dictionary = Dictionary::new(CJ_DATA);
+        // 焼肉
+        assert!(matches!(dictionary.value(&[0x713c, 0x8089]), Ok(Some(_))));
+        // 焼肉定
+        assert_eq!(dictionary.value(&[0x713c, 0x8089, 0x5b9a]), Ok(None));
+
+        let dictionary = Dictionary::new(KHMER_DATA);
+        // ភាសា
+        assert_eq!(
+            dictionary.value(&[0x1797, 0x17b6, 0x179f, 0x17b6]),
+            Err(Error::NoValues)
+        );
+    }
+
+    #[test]
+    fn entries_test() {
+        let dictionary = Dictionary::new(KHMER_DATA);
+        let entries = dictionary.entries();
+        assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+        for (word, value) in entries.iter().step_by(97) {
+            assert_eq!(*value, 0);
+            assert_eq!(
+                crate::DictionaryIterator::new(KHMER_DATA, word).next(),
+                Some(word.len())
+            );
+        }
+
+        let dictionary = Dictionary::new(CJ_DATA);
+        let entries = dictionary.entries();
+        for (word, value) in entries.iter().step_by(997) {
+            assert_eq!(dictionary.value(word), Ok(Some(*value)));
+        }
+    }
+
+    #[test]
+    fn try_new_test() {
+        assert!(Dictionary::try_new(KHMER_DATA).is_ok());
+        assert_eq!(
+            Dictionary::try_new(b"[package]").err(),
+            Some(Error::InvalidDictionary("not an ICU dictionary"))
+        );
+        let mut data = CJ_DATA.to_vec();
+        data.truncate(1000);
+        assert_eq!(
+            Dictionary::try_new(&data).err(),
+            Some(Error::InvalidDictionary("trie out of range"))
+        );
+        // Make the total size fit, then break the trie type.
+        data[144 + 12..144 + 16].copy_from_slice(&800u32.to_le_bytes());
+        data[144 + 16] = 5;
+        assert_eq!(
+            Dictionary::try_new(&data).err(),
+            Some(Error::InvalidDictionary("unknown type"))
+        );
+        data.truncate(100);
+        assert_eq!(
+            Dictionary::try_new(&data).err(),
+            Some(Error::InvalidDictionary("too small data"))
+        );
+    }
+
+    #[test]
+    fn out_of_range_test() {
+        let dictionary = Dictionary::new(KHMER_DATA);
+        assert_eq!(dictionary.transform(0x0061), -1);
+        assert_eq!(dictionary.transform(0x200d), 0xff);
+
+        let mut trie = dictionary.trie();
+        assert_eq!(trie.first(dictionary.data(), -1), TrieResult::NoMatch);
+        assert_eq!(trie.first(dictionary.data(), 0x20), TrieResult::NoValue);
+        assert_eq!(trie.next(dictionary.data(), -1), TrieResult::NoMatch);
+        assert_eq!(trie.next(dictionary.data(), 0xff), TrieResult::NoMatch);
+    }
+}
@@ -0,0 +1,546 @@
+use crate::dictionary::*;
+use crate::error::Error;
+
+// Bytes trie constants, see bytes_trie.rs.
+const BYTES_MIN_LINEAR_MATCH: u16 = 0x10;
+const BYTES_MIN_ONE_BYTE_VALUE_LEAD: i32 = 0x10;
+const BYTES_MAX_ONE_BYTE_VALUE: i32 = 0x40;
+const BYTES_MIN_TWO_BYTE_VALUE_LEAD: i32 = 0x51;
+const BYTES_MAX_TWO_BYTE_VALUE: i32 = 0x1aff;
+const BYTES_MIN_THREE_BYTE_VALUE_LEAD: i32 = 0x6c;
+const BYTES_MAX_THREE_BYTE_VALUE: i32 = 0x11ffff;
+const BYTES_FOUR_BYTE_VALUE_LEAD: i32 = 0x7e;
+const BYTES_FIVE_BYTE_VALUE_LEAD: i32 = 0x7f;
+const BYTES_MAX_ONE_BYTE_DELTA: usize = 0xbf;
+const BYTES_MIN_TWO_BYTE_DELTA_LEAD: usize = 0xc0;
+const BYTES_MAX_TWO_BYTE_DELTA: usize = 0x2fff;
+const BYTES_MIN_THREE_BYTE_DELTA_LEAD: usize = 0xf0;
+const BYTES_MAX_THREE_BYTE_DELTA: usize = 0xdffff;
+const BYTES_FOUR_BYTE_DELTA_LEAD: usize = 0xfe;
+const BYTES_FIVE_BYTE_DELTA_LEAD: usize = 0xff;
+
+// UChars trie constants, see uchars_trie.rs.
+const UCHARS_MIN_LINEAR_MATCH: u16 = 0x30;
+const UCHARS_MAX_ONE_UNIT_VALUE: i32 = 0x3fff;
+const UCHARS_MIN_TWO_UNIT_VALUE_LEAD: i32 = 0x4000;
+const UCHARS_MAX_TWO_UNIT_VALUE: i32 = 0x3ffeffff;
+const UCHARS_THREE_UNIT_VALUE_LEAD: i32 = 0x7fff;
+const UCHARS_MAX_ONE_UNIT_NODE_VALUE: i32 = 0xff;
+const UCHARS_MIN_TWO_UNIT_NODE_VALUE_LEAD: i32 = 0x4040;
+const UCHARS_MAX_TWO_UNIT_NODE_VALUE: i32 = 0xfdffff;
+const UCHARS_THREE_UNIT_NODE_VALUE_LEAD: i32 = 0x7fc0;
+const UCHARS_MAX_ONE_UNIT_DELTA: usize = 0xfbff;
+const UCHARS_MIN_TWO_UNIT_DELTA_LEAD: usize = 0xfc00;
+const UCHARS_MAX_TWO_UNIT_DELTA: usize = 0x3feffff;
+const UCHARS_THREE_UNIT_DELTA_LEAD: usize = 0xffff;
+
+const MAX_LINEAR_MATCH_LENGTH: usize = 0x10;
+const MAX_BRANCH_LINEAR_SUB_NODE_LENGTH: usize = 5;
+
+const HEADER_SIZE: usize = 0x20;
+const INDEXES_SIZE: usize = 8 * 4;
+
+/// Builds dictionaries in ICU's binary `.dict` format.
+///
+/// The tries are written like ICU's `BytesTrieBuilder`/`UCharsTrieBuilder`,
+/// except that identical sub-nodes are not shared.
+#[derive(Clone)]
+pub struct DictionaryBuilder {
+    trie_type: u32,
+    transform: u32,
+    has_values: bool,
+    entries: Vec<(Vec<u16>, i32)>,
+}
+
+impl DictionaryBuilder {
+    /// Creates a builder for a UChars trie dictionary, like `cjdict.dict`.
+    pub fn uchars() -> Self {
+        Self {
+            trie_type: TRIE_TYPE_UCHARS,
+            transform: 0,
+            has_values: false,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Creates a builder for a bytes trie dictionary whose characters are
+    /// stored as offsets from `offset`, like `khmerdict.dict`.
+    pub fn bytes(offset: u32) -> Self {
+        Self {
+            trie_type: TRIE_TYPE_BYTES,
+            transform: TRANSFORM_TYPE_OFFSET | offset,
+            has_values: false,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Creates a builder for the dictionary of the reversed words of
+    /// `dictionary`, as used for backward matching.
+    pub fn reversed(dictionary: &Dictionary) -> Self {
+        let entries = dictionary
+            .entries()
+            .into_iter()
+            .map(|(mut word, value)| {
+                word.reverse();
+                (word, value)
+            })
+            .collect();
+        Self {
+            trie_type: dictionary.trie_type(),
+            transform: dictionary.transform_constant(),
+            has_values: dictionary.has_values(),
+            entries,
+        }
+    }
+
+    /// Adds a word. If any word has a value, the dictionary is marked as
+    /// having values and words without one get 0.
+    pub fn insert(&mut self, word: &[u16], value: Option<i32>) -> &mut Self {
+        if value.is_some() {
+            self.has_values = true;
+        }
+        self.entries.push((word.to_vec(), value.unwrap_or(0)));
+        self
+    }
+
+    /// Writes the dictionary.
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (word, value) in self.entries.iter() {
+            if word.is_empty() {
+                continue;
+            }
+            let mut units = Vec::with_capacity(word.len());
+            for c in word.iter() {
+                let unit = transform_unit(self.transform, *c);
+                if unit < 0 {
+                    return Err(Error::OutOfRange(*c));
+                }
+                units.push(unit as u16);
+            }
+            entries.push((units, *value));
+        }
+        // Keep the last value of duplicated words.
+        entries.reverse();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.dedup_by(|a, b| a.0 == b.0);
+        if entries.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let trie = if self.trie_type == TRIE_TYPE_BYTES {
+            let mut writer = BytesWriter(Vec::new());
+            write_node(&mut writer, &entries, 0);
+            writer.0.reverse();
+            writer.0
+        } else {
+            let mut writer = UCharsWriter(Vec::new());
+            write_node(&mut writer, &entries, 0);
+            writer
+                .0
+                .iter()
+                .rev()
+                .flat_map(|u| u.to_le_bytes())
+                .collect()
+        };
+
+        let mut trie_type = self.trie_type;
+        if self.has_values {
+            trie_type |= TRIE_HAS_VALUES;
+        }
+        let total_size = (INDEXES_SIZE + trie.len()) as u32;
+        let mut data = Vec::with_capacity(HEADER_SIZE + total_size as usize);
+        // ICU data header: size, magic, and UDataInfo for the "Dict" format 1.
+        data.extend_from_slice(&(HEADER_SIZE as u16).to_le_bytes());
+        data.extend_from_slice(&[0xda, 0x27]);
+        data.extend_from_slice(&20u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 2, 0]);
+        data.extend_from_slice(b"Dict");
+        data.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        data.resize(HEADER_SIZE, 0);
+        let indexes = [
+            INDEXES_SIZE as u32,
+            total_size,
+            total_size,
+            total_size,
+            trie_type,
+            self.transform,
+            0,
+            0,
+        ];
+        for index in indexes.iter() {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        data.extend_from_slice(&trie);
+        Ok(data)
+    }
+}
+
+// Writes trie units back to front, so that jump deltas are known when they
+// are written. Offsets are counted from the end of the trie.
+trait TrieWriter {
+    const MIN_LINEAR_MATCH: u16;
+
+    fn len(&self) -> usize;
+    fn write(&mut self, units: &[u16]) -> usize;
+    fn write_value_and_final(&mut self, value: i32, is_final: bool) -> usize;
+    fn write_value_and_type(&mut self, value: Option<i32>, node: u16) -> usize;
+    fn write_delta_to(&mut self, jump_target: usize) -> usize;
+}
+
+struct BytesWriter(Vec<u8>);
+
+impl TrieWriter for BytesWriter {
+    const MIN_LINEAR_MATCH: u16 = BYTES_MIN_LINEAR_MATCH;
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn write(&mut self, units: &[u16]) -> usize {
+        self.0.extend(units.iter().rev().map(|u| *u as u8));
+        self.len()
+    }
+
+    fn write_value_and_final(&mut self, value: i32, is_final: bool) -> usize {
+        let is_final = is_final as i32;
+        if (0..=BYTES_MAX_ONE_BYTE_VALUE).contains(&value) {
+            return self
+                .write(&[(((BYTES_MIN_ONE_BYTE_VALUE_LEAD + value) << 1) | is_final) as u16]);
+        }
+        let mut bytes = if !(0..=0xffffff).contains(&value) {
+            vec![
+                BYTES_FIVE_BYTE_VALUE_LEAD,
+                value >> 24,
+                value >> 16,
+                value >> 8,
+                value,
+            ]
+        } else if value <= BYTES_MAX_TWO_BYTE_VALUE {
+            vec![BYTES_MIN_TWO_BYTE_VALUE_LEAD + (value >> 8), value]
+        } else if value <= BYTES_MAX_THREE_BYTE_VALUE {
+            vec![
+                BYTES_MIN_THREE_BYTE_VALUE_LEAD + (value >> 16),
+                value >> 8,
+                value,
+            ]
+        } else {
+            vec![BYTES_FOUR_BYTE_VALUE_LEAD, value >> 16, value >> 8, value]
+        };
+        bytes[0] = (bytes[0] << 1) | is_final;
+        let units: Vec<u16> = bytes.iter().map(|b| (*b & 0xff) as u16).collect();
+        self.write(&units)
+    }
+
+    // Match nodes can't have values, so an intermediate value precedes the node.
+    fn write_value_and_type(&mut self, value: Option<i32>, node: u16) -> usize {
+        let offset = self.write(&[node]);
+        match value {
+            Some(value) => self.write_value_and_final(value, false),
+            None => offset,
+        }
+    }
+
+    fn write_delta_to(&mut self, jump_target: usize) -> usize {
+        let delta = self.len() - jump_target;
+        let bytes = if delta <= BYTES_MAX_ONE_BYTE_DELTA {
+            vec![delta]
+        } else if delta <= BYTES_MAX_TWO_BYTE_DELTA {
+            vec![BYTES_MIN_TWO_BYTE_DELTA_LEAD + (delta >> 8), delta]
+        } else if delta <= BYTES_MAX_THREE_BYTE_DELTA {
+            vec![
+                BYTES_MIN_THREE_BYTE_DELTA_LEAD + (delta >> 16),
+                delta >> 8,
+                delta,
+            ]
+        } else if delta <= 0xffffff {
+            vec![BYTES_FOUR_BYTE_DELTA_LEAD, delta >> 16, delta >> 8, delta]
+        } else {
+            vec![
+                BYTES_FIVE_BYTE_DELTA_LEAD,
+                delta >> 24,
+                delta >> 16,
+                delta >> 8,
+                delta,
+            ]
+        };
+        let units: Vec<u16> = bytes.iter().map(|b| (*b & 0xff) as u16).collect();
+        self.write(&units)
+    }
+}
+
+struct UCharsWriter(Vec<u16>);
+
+impl TrieWriter for UCharsWriter {
+    const MIN_LINEAR_MATCH: u16 = UCHARS_MIN_LINEAR_MATCH;
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn write(&mut self, units: &[u16]) -> usize {
+        self.0.extend(units.iter().rev());
+        self.len()
+    }
+
+    fn write_value_and_final(&mut self, value: i32, is_final: bool) -> usize {
+        let is_final = (is_final as u16) << 15;
+        if (0..=UCHARS_MAX_ONE_UNIT_VALUE).contains(&value) {
+            return self.write(&[value as u16 | is_final]);
+        }
+        if !(0..=UCHARS_MAX_TWO_UNIT_VALUE).contains(&value) {
+            self.write(&[
+                UCHARS_THREE_UNIT_VALUE_LEAD as u16 | is_final,
+                (value >> 16) as u16,
+                value as u16,
+            ])
+        } else {
+            self.write(&[
+                (UCHARS_MIN_TWO_UNIT_VALUE_LEAD + (value >> 16)) as u16 | is_final,
+                value as u16,
+            ])
+        }
+    }
+
+    fn write_value_and_type(&mut self, value: Option<i32>, node: u16) -> usize {
+        match value {
+            None => self.write(&[node]),
+            Some(value) if !(0..=UCHARS_MAX_TWO_UNIT_NODE_VALUE).contains(&value) => self.write(&[
+                UCHARS_THREE_UNIT_NODE_VALUE_LEAD as u16 | node,
+                (value >> 16) as u16,
+                value as u16,
+            ]),
+            Some(value) if value <= UCHARS_MAX_ONE_UNIT_NODE_VALUE => {
+                self.write(&[((value + 1) << 6) as u16 | node])
+            }
+            Some(value) => self.write(&[
+                (UCHARS_MIN_TWO_UNIT_NODE_VALUE_LEAD + ((value >> 10) & 0x7fc0)) as u16 | node,
+                value as u16,
+            ]),
+        }
+    }
+
+    fn write_delta_to(&mut self, jump_target: usize) -> usize {
+        let delta = self.len() - jump_target;
+        if delta <= UCHARS_MAX_ONE_UNIT_DELTA {
+            self.write(&[delta as u16])
+        } else if delta <= UCHARS_MAX_TWO_UNIT_DELTA {
+            self.write(&[
+                (UCHARS_MIN_TWO_UNIT_DELTA_LEAD + (delta >> 16)) as u16,
+                delta as u16,
+            ])
+        } else {
+            self.write(&[
+                UCHARS_THREE_UNIT_DELTA_LEAD as u16,
+                (delta >> 16) as u16,
+                delta as u16,
+            ])
+        }
+    }
+}
+
+// Writes the node for the sorted, distinct `entries`, which share their
+// first `unit_index` units. Returns the node's offset.
+fn write_node<W: TrieWriter>(
+    writer: &mut W,
+    entries: &[(Vec<u16>, i32)],
+    unit_index: usize,
+) -> usize {
+    let mut value = None;
+    let mut entries = entries;
+    if entries[0].0.len() == unit_index {
+        if entries.len() == 1 {
+            return writer.write_value_and_final(entries[0].1, true);
+        }
+        value = Some(entries[0].1);
+        entries = &entries[1..];
+    }
+
+    let first = &entries[0].0;
+    let last = &entries[entries.len() - 1].0;
+    let mut common = unit_index;
+    while common < first.len() && common < last.len() && first[common] == last[common] {
+        common += 1;
+    }
+
+    let node = if common > unit_index {
+        let length = (common - unit_index).min(MAX_LINEAR_MATCH_LENGTH);
+        write_node(writer, entries, unit_index + length);
+        writer.write(&first[unit_index..unit_index + length]);
+        W::MIN_LINEAR_MATCH + length as u16 - 1
+    } else {
+        let length = count_units(entries, unit_index);
+        write_branch_sub_node(writer, entries, unit_index, length);
+        if length - 1 < W::MIN_LINEAR_MATCH as usize {
+            (length - 1) as u16
+        } else {
+            writer.write(&[(length - 1) as u16]);
+            0
+        }
+    };
+    writer.write_value_and_type(value, node)
+}
+
+fn write_branch_sub_node<W: TrieWriter>(
+    writer: &mut W,
+    entries: &[(Vec<u16>, i32)],
+    unit_index: usize,
+    length: usize,
+) -> usize {
+    let mut middle_units = Vec::new();
+    let mut less_than = Vec::new();
+    let mut entries = entries;
+    let mut length = length;
+    while length > MAX_BRANCH_LINEAR_SUB_NODE_LENGTH {
+        // Branch on the middle unit.
+        let i = skip_units(entries, unit_index, length / 2);
+        middle_units.push(entries[i].0[unit_index]);
+        less_than.push(write_branch_sub_node(
+            writer,
+            &entries[..i],
+            unit_index,
+            length / 2,
+        ));
+        entries = &entries[i..];
+        length -= length / 2;
+    }
+
+    // The start of each unit's entries, and whether it is a final value.
+    let mut starts = Vec::with_capacity(length);
+    let mut is_final = Vec::with_capacity(length);
+    let mut start = 0;
+    for _ in 0..length - 1 {
+        let end = skip_units(&entries[start..], unit_index, 1) + start;
+        starts.push(start);
+        is_final.push(start == end - 1 && entries[start].0.len() == unit_index + 1);
+        start = end;
+    }
+    starts.push(start);
+
+    // Write the sub-nodes in reverse order, so that the first unit's jump
+    // delta is the shortest. The last unit's sub-node follows it directly.
+    let mut jump_targets = vec![0; length - 1];
+    for i in (0..length - 1).rev() {
+        if !is_final[i] {
+            jump_targets[i] =
+                write_node(writer, &entries[starts[i]..starts[i + 1]], unit_index + 1);
+        }
+    }
+    write_node(writer, &entries[start..], unit_index + 1);
+    let mut offset = writer.write(&[entries[start].0[unit_index]]);
+    for i in (0..length - 1).rev() {
+        let entry = &entries[starts[i]];
+        if is_final[i] {
+            writer.write_value_and_final(entry.1, true);
+        } else {
+            let delta = (offset - jump_targets[i]) as i32;
+            writer.write_value_and_final(delta, false);
+        }
+        offset = writer.write(&[entry.0[unit_index]]);
+    }
+
+    while let Some(target) = less_than.pop() {
+        writer.write_delta_to(target);
+        offset = writer.write(&[middle_units.pop().unwrap()]);
+    }
+    offset
+}
+
+// Returns the number of distinct units at `unit_index`.
+fn count_units(entries: &[(Vec<u16>, i32)], unit_index: usize) -> usize {
+    let mut count = 1;
+    for w in entries.windows(2) {
+        if w[0].0[unit_index] != w[1].0[unit_index] {
+            count += 1;
+        }
+    }
+    count
+}
+
+// Returns the index of the first entry after the first `count` distinct units.
+fn skip_units(entries: &[(Vec<u16>, i32)], unit_index: usize, count: usize) -> usize {
+    let mut count = count;
+    let mut i = 0;
+    loop {
+        let unit = entries[i].0[unit_index];
+        while i < entries.len() && entries[i].0[unit_index] == unit {
+            i += 1;
+        }
+        count -= 1;
+        if count == 0 {
+            return i;
+        }
+    }
+}
+
+// Builds a dictionary of `words` without values, for tests.
+#[cfg(test)]
+pub(crate) fn build_words(mut builder: DictionaryBuilder, words: &[&str]) -> Vec<u8> {
+    for word in words.iter() {
+        builder.insert(&word.encode_utf16().collect::<Vec<u16>>(), None);
+    }
+    builder.build().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary_builder::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn build_test() {
+        let words = [
+            "a",
+            "ab",
+            "abc",
+            "abd",
+            "b",
+            "bcdefghijklmnopqrstuvwxyz",
+            "c",
+            "d",
+            "e",
+            "f",
+            "g",
+        ];
+        let mut builder = DictionaryBuilder::uchars();
+        for (i, word) in words.iter().enumerate() {
+            builder.insert(&utf16(word), Some(i as i32 * 1000));
+        }
+        let data = builder.build().unwrap();
+        let dictionary = Dictionary::new(&data);
+        assert!(dictionary.has_values());
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(dictionary.value(&utf16(word)), Ok(Some(i as i32 * 1000)));
+        }
+        assert_eq!(dictionary.value(&utf16("abcd")), Ok(None));
+        assert_eq!(dictionary.value(&utf16("h")), Ok(None));
+
+        let data = build_words(DictionaryBuilder::bytes(0x0e00), &["กา", "กาก", "ขา"]);
+        let dictionary = Dictionary::new(&data);
+        assert!(!dictionary.has_values());
+        assert_eq!(dictionary.entries().len(), 3);
+        assert_eq!(
+            DictionaryBuilder::bytes(0x0e00)
+                .insert(&utf16("a"), None)
+                .build(),
+            Err(Error::OutOfRange(0x61))
+        );
+        assert_eq!(DictionaryBuilder::uchars().build(), Err(Error::Empty));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for data in [&KHMER_DATA[..], &CJ_DATA[..]].iter() {
+            let dictionary = Dictionary::new(data);
+            let mut builder = DictionaryBuilder::reversed(&dictionary);
+            builder = DictionaryBuilder::reversed(&Dictionary::new(&builder.build().unwrap()));
+            let rebuilt = builder.build().unwrap();
+            assert_eq!(Dictionary::new(&rebuilt).entries(), dictionary.entries());
+        }
+    }
+}
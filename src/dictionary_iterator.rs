@@ -1,35 +1,13 @@
-use crate::bytes_trie::*;
+use crate::dictionary::*;
+use crate::grapheme::*;
 use crate::trie::*;
-use crate::uchars_trie::*;
-
-const TRIE_TYPE_BYTES: u32 = 0;
-const TRIE_TYPE_UCHARS: u32 = 1;
-const TRIE_TYPE_MASK: u32 = 7;
-
-const TRANSFORM_TYPE_OFFSET: u32 = 0x1000000;
-const TRANSFORM_TYPE_MASK: u32 = 0x7f000000;
-const TRANSFORM_OFFSET_MASK: u32 = 0x1fffff;
-
-#[repr(C)]
-struct TrieHeader {
-    pub icu_header: [u8; 0x90],
-    pub trie_offset: u32,
-    pub reserved1: u32,
-    pub reserved2: u32,
-    pub total_size: u32,
-    pub trie_type: u32,
-    pub transform: u32,
-    pub reserved6: u32,
-    pub reserved7: u32,
-}
 
 #[derive(Clone)]
 pub struct DictionaryIterator<'a> {
-    trie: Box<dyn Trie>,
+    trie: TrieCursor,
     iter: &'a [u16],
     front_offset: usize,
-    transform: u32,
-    dictionary: &'a [u8],
+    dictionary: Dictionary<'a>,
 }
 
 impl<'a> Iterator for DictionaryIterator<'a> {
@@ -48,25 +26,41 @@ impl<'a> Iterator for DictionaryIterator<'a> {
                 self.front_offset = self.iter.len();
                 return Some(self.front_offset);
             }
+            let pos = self.front_offset + i;
+            if i > 0 {
+                let next = self
+                    .dictionary
+                    .skip_ignorable(self.iter, pos, self.iter.len());
+                if next > pos {
+                    i = next - self.front_offset;
+                    continue;
+                }
+            }
             let result = match i {
                 0 => self.trie.first(
-                    self.dictionary,
-                    self.transform(self.iter[i + self.front_offset]),
+                    self.dictionary.data(),
+                    self.dictionary.transform(self.iter[pos]),
                 ),
                 _ => self.trie.next(
-                    self.dictionary,
-                    self.transform(self.iter[i + self.front_offset]),
+                    self.dictionary.data(),
+                    self.dictionary.transform(self.iter[pos]),
                 ),
             };
-            if result == TrieResult::FinalValue {
-                self.front_offset += i + 1;
+            // Ignorable characters after a word belong to it, and words have
+            // to end on a grapheme cluster boundary.
+            let word_end = self
+                .dictionary
+                .skip_ignorable(self.iter, pos + 1, self.iter.len());
+            let is_boundary = || is_grapheme_boundary(self.iter, word_end);
+            if result == TrieResult::FinalValue && is_boundary() {
+                self.front_offset = word_end;
                 return Some(self.front_offset);
             }
-            if result == TrieResult::Intermediate {
-                intermediate_length = i + 1;
+            if result == TrieResult::Intermediate && is_boundary() {
+                intermediate_length = word_end - self.front_offset;
                 // continue for better string
             }
-            if result == TrieResult::NoMatch {
+            if result == TrieResult::NoMatch || result == TrieResult::FinalValue {
                 break;
             }
             i += 1;
@@ -83,53 +77,24 @@ impl<'a> Iterator for DictionaryIterator<'a> {
 
 impl<'a> DictionaryIterator<'a> {
     pub fn new(dictionary: &'a [u8], input: &'a [u16]) -> Self {
-        if dictionary.len() < core::mem::size_of::<TrieHeader>() {
-            panic!("too small data");
-        }
-        let header = unsafe { &*(dictionary as *const [u8] as *const TrieHeader) };
-        let trie_type = header.trie_type & TRIE_TYPE_MASK;
-        match trie_type {
-            TRIE_TYPE_BYTES => Self {
-                trie: Box::new(BytesTrie::new(0x90 + header.trie_offset as usize)),
-                iter: input,
-                front_offset: 0,
-                transform: header.transform,
-                dictionary: dictionary,
-            },
-            TRIE_TYPE_UCHARS => Self {
-                trie: Box::new(UCharsTrie::new(0x90 + header.trie_offset as usize)),
-                iter: input,
-                front_offset: 0,
-                transform: header.transform,
-                dictionary: dictionary,
-            },
-            _ => panic!("unknown type"),
-        }
+        Self::with_dictionary(Dictionary::new(dictionary), input)
     }
 
-    fn transform(&self, c: u16) -> i32 {
-        if self.transform & TRANSFORM_TYPE_MASK == TRANSFORM_TYPE_OFFSET {
-            match c {
-                0x200c => 0xfe,
-                0x200d => 0xff,
-                _ => {
-                    let delta = (c as u32 - (self.transform & TRANSFORM_OFFSET_MASK)) as i32;
-                    if delta < 0 || delta > 0xfd {
-                        -1
-                    } else {
-                        delta
-                    }
-                }
-            }
-        } else {
-            c as i32
+    pub fn with_dictionary(dictionary: Dictionary<'a>, input: &'a [u16]) -> Self {
+        Self {
+            trie: dictionary.trie(),
+            iter: input,
+            front_offset: 0,
+            dictionary,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::dictionary_builder::*;
     use crate::dictionary_iterator::*;
+    use crate::error::*;
     const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
     const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
     const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
@@ -167,4 +132,79 @@ mod tests {
         assert_eq!(iterator.next(), Some(5));
         assert_eq!(iterator.next(), Some(8));
     }
+
+    #[test]
+    fn out_of_range_test() {
+        // ភាសាខ្មែរabc
+        const KM_LATIN_STR: [u16; 12] = [
+            0x1797, 0x17b6, 0x179f, 0x17b6, 0x1781, 0x17d2, 0x1798, 0x17c2, 0x179a, 0x0061, 0x0062,
+            0x0063,
+        ];
+        let mut iterator = DictionaryIterator::new(KHMER_DATA, &KM_LATIN_STR);
+        assert_eq!(iterator.next(), Some(9));
+        assert_eq!(iterator.next(), None);
+
+        // abcភាសា
+        const LATIN_KM_STR: [u16; 7] = [0x0061, 0x0062, 0x0063, 0x1797, 0x17b6, 0x179f, 0x17b6];
+        let mut iterator = DictionaryIterator::new(KHMER_DATA, &LATIN_KM_STR);
+        assert_eq!(iterator.next(), None);
+
+        // The dictionary has a word with ZWJ (U+17A0 U+200D U+17CA U+17B9 U+17C7).
+        const ZWJ_STR: [u16; 5] = [0x17a0, 0x200d, 0x17ca, 0x17b9, 0x17c7];
+        let mut iterator = DictionaryIterator::new(KHMER_DATA, &ZWJ_STR);
+        assert_eq!(iterator.next(), Some(5));
+
+        // A Latin letter at the position of the ZWJ must not match that word.
+        const LATIN_ZWJ_STR: [u16; 5] = [0x17a0, 0x0061, 0x17ca, 0x17b9, 0x17c7];
+        let mut iterator = DictionaryIterator::new(KHMER_DATA, &LATIN_ZWJ_STR);
+        assert_eq!(iterator.next(), None);
+    }
+
+    #[test]
+    fn grapheme_test() {
+        // ក្ស is a word, but it ends inside the cluster ក្សា.
+        let data = build_words(DictionaryBuilder::bytes(0x1780), &["ក្ស"]);
+        let utf16: Vec<u16> = "ក្សា".encode_utf16().collect();
+        let mut iter = DictionaryIterator::new(&data, &utf16);
+        assert_eq!(iter.next(), None);
+        let utf16: Vec<u16> = "ក្ស".encode_utf16().collect();
+        let mut iter = DictionaryIterator::new(&data, &utf16);
+        assert_eq!(iter.next(), Some(3));
+    }
+
+    #[test]
+    fn ignorable_test() {
+        const IGNORABLE: &[char] = &['\u{200b}', '\u{00ad}', '\u{fe00}', '\u{e0100}'];
+        let khmer = Dictionary::new(KHMER_DATA);
+        let utf16: Vec<u16> = "ភា\u{200b}សាខ្មែរ\u{00ad}ភាសា".encode_utf16().collect();
+        let mut iter = DictionaryIterator::with_dictionary(khmer, &utf16);
+        assert_eq!(iter.next(), Some(2));
+        let mut iter = DictionaryIterator::with_dictionary(khmer.with_ignorable(IGNORABLE), &utf16);
+        assert_eq!(iter.next(), Some(11));
+        assert_eq!(iter.next(), Some(15));
+
+        // Variation selectors, including a supplementary one.
+        let cj = Dictionary::new(CJ_DATA);
+        let utf16: Vec<u16> = "焼\u{fe00}肉\u{e0100}定食".encode_utf16().collect();
+        let mut iter = DictionaryIterator::with_dictionary(cj, &utf16);
+        assert_eq!(iter.next(), None);
+        let mut iter = DictionaryIterator::with_dictionary(cj.with_ignorable(IGNORABLE), &utf16);
+        assert_eq!(iter.next(), Some(5));
+        assert_eq!(iter.next(), Some(7));
+
+        // A word never starts with an ignorable character.
+        let utf16: Vec<u16> = "\u{200b}ភាសា".encode_utf16().collect();
+        let mut iter = DictionaryIterator::with_dictionary(khmer.with_ignorable(IGNORABLE), &utf16);
+        assert_eq!(iter.next(), None);
+        assert_eq!(
+            khmer.with_ignorable(IGNORABLE).value(&utf16),
+            Err(Error::NoValues)
+        );
+        let utf16: Vec<u16> = "焼\u{fe00}肉".encode_utf16().collect();
+        assert_eq!(cj.value(&utf16), Ok(None));
+        assert_eq!(
+            cj.with_ignorable(IGNORABLE).value(&utf16),
+            cj.value(&[0x713c, 0x8089])
+        );
+    }
 }
@@ -0,0 +1,648 @@
+use crate::dictionary::*;
+use crate::grapheme::*;
+use crate::trie::*;
+
+// A port of the dictionary break engines of ICU's dictbe.cpp. Lengths are
+// counted in UTF-16 units, which are code points for the scripts below.
+
+// How many words in a row are "good enough"?
+const LOOKAHEAD: usize = 3;
+// Will not combine a non-word with a preceding dictionary word longer than this.
+const ROOT_COMBINE_THRESHOLD: usize = 3;
+// Will not combine a non-word that shares at least this much prefix with a
+// dictionary word, with a preceding word.
+const PREFIX_COMBINE_THRESHOLD: usize = 3;
+// Minimum word size.
+const MIN_WORD: usize = 2;
+// Minimum number of characters for two words.
+const MIN_WORD_SPAN: usize = MIN_WORD * 2;
+// The most candidate words kept at one position.
+const POSSIBLE_WORD_LIST_MAX: usize = 20;
+
+// The character classes of a script, as inclusive ranges.
+pub(crate) struct Rules {
+    // The characters the engine segments: the script's characters with
+    // Line_Break=SA.
+    pub word: &'static [(u16, u16)],
+    // Characters that can begin a word when the engine resynchronizes.
+    pub begin: &'static [(u16, u16)],
+    // Word characters that can't end a word.
+    pub not_end: &'static [(u16, u16)],
+    // Combining marks, never separated from the preceding character.
+    pub mark: &'static [(u16, u16)],
+    // A mark abbreviating the preceding word, attached to it when no
+    // dictionary word follows.
+    pub abbreviation: Option<u16>,
+    // A mark repeating the preceding word, attached likewise.
+    pub repetition: Option<u16>,
+}
+
+pub(crate) const KHMER: Rules = Rules {
+    word: &[(0x1780, 0x17d3), (0x17d7, 0x17d7), (0x17dc, 0x17dd)],
+    begin: &[(0x1780, 0x17b3)],
+    // KHMER SIGN COENG combines with the following consonant.
+    not_end: &[(0x17d2, 0x17d2)],
+    mark: &[(0x17b4, 0x17d3), (0x17dd, 0x17dd)],
+    abbreviation: None,
+    repetition: None,
+};
+
+pub(crate) const LAO: Rules = Rules {
+    word: &[(0x0e81, 0x0ecf), (0x0edc, 0x0edf)],
+    // Consonants, digraphs and pre-base vowels.
+    begin: &[(0x0e81, 0x0eae), (0x0edc, 0x0edd), (0x0ec0, 0x0ec4)],
+    // Pre-base vowels are written before the consonant they follow in speech.
+    not_end: &[(0x0ec0, 0x0ec4)],
+    mark: &[(0x0eb1, 0x0eb1), (0x0eb4, 0x0ebc), (0x0ec8, 0x0ece)],
+    // LAO ELLIPSIS and LAO KO LA. ICU's Lao engine has no suffixes; these
+    // follow the Thai engine.
+    abbreviation: Some(0x0eaf),
+    repetition: Some(0x0ec6),
+};
+
+pub(crate) const THAI: Rules = Rules {
+    word: &[(0x0e01, 0x0e3a), (0x0e40, 0x0e4e)],
+    // Consonants and pre-base vowels.
+    begin: &[(0x0e01, 0x0e2e), (0x0e40, 0x0e44)],
+    // MAI HAN-AKAT and the pre-base vowels.
+    not_end: &[(0x0e31, 0x0e31), (0x0e40, 0x0e44)],
+    mark: &[(0x0e31, 0x0e31), (0x0e34, 0x0e3a), (0x0e47, 0x0e4e)],
+    // PAIYANNOI and MAIYAMOK.
+    abbreviation: Some(0x0e2f),
+    repetition: Some(0x0e46),
+};
+
+pub(crate) const BURMESE: Rules = Rules {
+    word: &[
+        (0x1000, 0x103f),
+        (0x104c, 0x108f),
+        (0x109a, 0x109f),
+        (0xa9e0, 0xa9ef),
+        (0xa9fa, 0xa9fe),
+        (0xaa60, 0xaa7f),
+    ],
+    // Consonants and independent vowels.
+    begin: &[(0x1000, 0x102a)],
+    not_end: &[],
+    // Vowel signs, medials, asat and tone marks.
+    mark: &[
+        (0x102b, 0x103e),
+        (0x1056, 0x1059),
+        (0x105e, 0x1060),
+        (0x1062, 0x1064),
+        (0x1067, 0x106d),
+        (0x1071, 0x1074),
+        (0x1082, 0x108d),
+        (0x108f, 0x108f),
+        (0x109a, 0x109d),
+        (0xa9e5, 0xa9e5),
+        (0xaa7b, 0xaa7d),
+    ],
+    abbreviation: None,
+    repetition: None,
+};
+
+fn contains(set: &[(u16, u16)], c: u16) -> bool {
+    set.iter()
+        .any(|(first, last)| (*first..=*last).contains(&c))
+}
+
+impl Rules {
+    fn is_word(&self, c: u16) -> bool {
+        contains(self.word, c)
+    }
+
+    fn is_begin(&self, c: u16) -> bool {
+        contains(self.begin, c)
+    }
+
+    fn is_end(&self, c: u16) -> bool {
+        self.is_word(c) && !contains(self.not_end, c)
+    }
+
+    fn is_mark(&self, c: u16) -> bool {
+        c == 0x20 || contains(self.mark, c)
+    }
+
+    fn is_suffix(&self, c: u16) -> bool {
+        Some(c) == self.abbreviation || Some(c) == self.repetition
+    }
+}
+
+// The dictionary words at one position of the text.
+#[derive(Default)]
+struct PossibleWord {
+    // Candidate lengths, shortest first.
+    lengths: Lengths,
+    // The longest match with a dictionary word, including the character
+    // that failed to match.
+    prefix: usize,
+    // The position of the candidates.
+    offset: Option<usize>,
+    // The preferred candidate.
+    mark: usize,
+    // The candidate being looked at.
+    current: usize,
+}
+
+// Up to POSSIBLE_WORD_LIST_MAX lengths, without allocating.
+#[derive(Default)]
+struct Lengths {
+    lengths: [usize; POSSIBLE_WORD_LIST_MAX],
+    len: usize,
+}
+
+impl Lengths {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn last(&self) -> Option<&usize> {
+        self.lengths[..self.len].last()
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push(&mut self, length: usize) {
+        self.lengths[self.len] = length;
+        self.len += 1;
+    }
+}
+
+impl core::ops::Index<usize> for Lengths {
+    type Output = usize;
+
+    fn index(&self, index: usize) -> &usize {
+        &self.lengths[..self.len][index]
+    }
+}
+
+impl PossibleWord {
+    // Finds the candidates at `pos` and moves `pos` after the longest one.
+    fn candidates(
+        &mut self,
+        dictionary: &Dictionary,
+        input: &[u16],
+        pos: &mut usize,
+        end: usize,
+    ) -> usize {
+        let start = *pos;
+        if self.offset != Some(start) {
+            self.offset = Some(start);
+            self.find(dictionary, input, start, end);
+        }
+        if let Some(length) = self.lengths.last() {
+            *pos = start + length;
+        }
+        self.current = self.lengths.len().saturating_sub(1);
+        self.mark = self.current;
+        self.lengths.len()
+    }
+
+    fn find(&mut self, dictionary: &Dictionary, input: &[u16], start: usize, end: usize) {
+        let data = dictionary.data();
+        let mut trie = dictionary.trie();
+        self.lengths.clear();
+        let mut length = 0;
+        while start + length < end {
+            if length > 0 {
+                let next = dictionary.skip_ignorable(input, start + length, end);
+                if next > start + length {
+                    length = next - start;
+                    continue;
+                }
+            }
+            let c = dictionary.transform(input[start + length]);
+            let result = match length {
+                0 => trie.first(data, c),
+                _ => trie.next(data, c),
+            };
+            length += 1;
+            match result {
+                TrieResult::FinalValue | TrieResult::Intermediate => {
+                    // Ignorable characters after a word belong to it.
+                    let word_end = dictionary.skip_ignorable(input, start + length, end);
+                    if self.lengths.len() < POSSIBLE_WORD_LIST_MAX {
+                        self.lengths.push(word_end - start);
+                    }
+                    if result == TrieResult::FinalValue {
+                        break;
+                    }
+                }
+                TrieResult::NoMatch => break,
+                TrieResult::NoValue => {}
+            }
+        }
+        self.prefix = length;
+    }
+
+    // Moves `pos` after the marked candidate and returns its length.
+    fn accept_marked(&self, pos: &mut usize) -> usize {
+        *pos = self.offset.unwrap() + self.lengths[self.mark];
+        self.lengths[self.mark]
+    }
+
+    // Moves `pos` after the next shorter candidate, if there is one.
+    fn back_up(&mut self, pos: &mut usize) -> bool {
+        if self.current > 0 {
+            self.current -= 1;
+            *pos = self.offset.unwrap() + self.lengths[self.current];
+            return true;
+        }
+        false
+    }
+
+    fn mark_current(&mut self) {
+        self.mark = self.current;
+    }
+
+    fn longest_prefix(&self) -> usize {
+        self.prefix
+    }
+}
+
+// Appends the boundaries in start+1..=end to `boundaries`. Runs of the
+// script's word characters are divided up by the engine, anything else is
+// skipped a grapheme cluster at a time. Unlike ICU, boundaries inside a
+// grapheme cluster, such as before a subjoined consonant, are dropped.
+pub(crate) fn segment(
+    dictionary: &Dictionary,
+    rules: &Rules,
+    input: &[u16],
+    start: usize,
+    end: usize,
+    boundaries: &mut Vec<usize>,
+) {
+    let first = boundaries.len();
+    let mut pos = start;
+    while pos < end {
+        if rules.is_word(input[pos]) {
+            // Ignorable characters don't end a run.
+            let mut run_end = pos;
+            while run_end < end {
+                if rules.is_word(input[run_end]) {
+                    run_end += 1;
+                } else {
+                    let next = dictionary.skip_ignorable(input, run_end, end);
+                    if next == run_end {
+                        break;
+                    }
+                    run_end = next;
+                }
+            }
+            divide_up_dictionary_range(dictionary, rules, input, pos, run_end, boundaries);
+            pos = run_end;
+        } else {
+            pos = next_cluster(input, pos, end);
+        }
+        boundaries.push(pos);
+    }
+    // Drop the boundaries inside grapheme clusters in place.
+    let mut kept = first;
+    for i in first..boundaries.len() {
+        if is_grapheme_boundary(input, boundaries[i]) {
+            boundaries[kept] = boundaries[i];
+            kept += 1;
+        }
+    }
+    boundaries.truncate(kept);
+}
+
+// Appends the boundaries inside input[start..end], a run of word characters.
+fn divide_up_dictionary_range(
+    dictionary: &Dictionary,
+    rules: &Rules,
+    input: &[u16],
+    start: usize,
+    end: usize,
+    boundaries: &mut Vec<usize>,
+) {
+    if end - start < MIN_WORD_SPAN {
+        // Not enough characters for two words.
+        return;
+    }
+
+    let first = boundaries.len();
+    let mut words: [PossibleWord; LOOKAHEAD] = Default::default();
+    let mut words_found = 0;
+    let mut pos = start;
+    while pos < end {
+        let current = pos;
+        let mut word_length = 0;
+
+        // Look for candidate words at the current position.
+        let candidates =
+            words[words_found % LOOKAHEAD].candidates(dictionary, input, &mut pos, end);
+        if candidates == 1 {
+            word_length = words[words_found % LOOKAHEAD].accept_marked(&mut pos);
+            words_found += 1;
+        } else if candidates > 1 {
+            // See which one can take us forward the most words.
+            mark_best(&mut words, words_found, dictionary, input, &mut pos, end);
+            word_length = words[words_found % LOOKAHEAD].accept_marked(&mut pos);
+            words_found += 1;
+        }
+
+        // If the next text isn't a dictionary word, combine it with the word
+        // just found, unless that is longer than the threshold.
+        if pos < end && word_length < ROOT_COMBINE_THRESHOLD {
+            let word = &mut words[words_found % LOOKAHEAD];
+            if word.candidates(dictionary, input, &mut pos, end) == 0
+                && (word_length == 0 || word.longest_prefix() < PREFIX_COMBINE_THRESHOLD)
+            {
+                // Look for a plausible word boundary.
+                let mut remaining = end - (current + word_length);
+                let mut chars = 0;
+                loop {
+                    let pc = input[pos];
+                    pos += 1;
+                    chars += 1;
+                    remaining -= 1;
+                    if remaining == 0 {
+                        break;
+                    }
+                    let uc = input[pos];
+                    if rules.is_end(pc) && rules.is_begin(uc) {
+                        // Maybe. See if it's in the dictionary.
+                        let candidates = words[(words_found + 1) % LOOKAHEAD]
+                            .candidates(dictionary, input, &mut pos, end);
+                        pos = current + word_length + chars;
+                        if candidates > 0 {
+                            break;
+                        }
+                    }
+                }
+
+                // Bump the word count if there wasn't already one.
+                if word_length == 0 {
+                    words_found += 1;
+                }
+                word_length += chars;
+            } else {
+                // Back up to where we were for the next iteration.
+                pos = current + word_length;
+            }
+        }
+
+        // Never stop before a combining mark.
+        while pos < end && rules.is_mark(input[pos]) {
+            pos += 1;
+            word_length += 1;
+        }
+
+        // Look ahead for possible suffixes if a dictionary word does not
+        // follow. This is done here rather than by a rule so that the
+        // resynchronization above still works when a suffix character is a
+        // typo in the middle of a word.
+        if pos < end && word_length > 0 {
+            if words[words_found % LOOKAHEAD].candidates(dictionary, input, &mut pos, end) == 0
+                && rules.is_suffix(input[pos])
+            {
+                let mut uc = Some(input[pos]);
+                if uc == rules.abbreviation && !rules.is_suffix(input[pos - 1]) {
+                    pos += 1;
+                    word_length += 1;
+                    uc = input[..end].get(pos).cloned();
+                }
+                if uc == rules.repetition && Some(input[pos - 1]) != rules.repetition {
+                    pos += 1;
+                    word_length += 1;
+                }
+            } else {
+                pos = current + word_length;
+            }
+        }
+
+        if word_length > 0 {
+            boundaries.push(current + word_length);
+        }
+    }
+
+    // The end of the range is added by the caller.
+    if boundaries.len() > first && *boundaries.last().unwrap() >= end {
+        boundaries.pop();
+    }
+}
+
+// Marks the candidate of words[found] that is followed by the most words,
+// looking ahead up to two words.
+fn mark_best(
+    words: &mut [PossibleWord; LOOKAHEAD],
+    found: usize,
+    dictionary: &Dictionary,
+    input: &[u16],
+    pos: &mut usize,
+    end: usize,
+) {
+    if *pos >= end {
+        return;
+    }
+    loop {
+        if words[(found + 1) % LOOKAHEAD].candidates(dictionary, input, pos, end) > 0 {
+            // Followed by another dictionary word; a good candidate.
+            words[found % LOOKAHEAD].mark_current();
+            if *pos >= end {
+                return;
+            }
+            // See if any of the possible second words is followed by a third.
+            loop {
+                if words[(found + 2) % LOOKAHEAD].candidates(dictionary, input, pos, end) > 0 {
+                    words[found % LOOKAHEAD].mark_current();
+                    return;
+                }
+                if !words[(found + 1) % LOOKAHEAD].back_up(pos) {
+                    break;
+                }
+            }
+        }
+        if !words[found % LOOKAHEAD].back_up(pos) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary_builder::*;
+    use crate::engine::*;
+    use crate::segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+
+    // Expected boundaries, from ICU's word break iterator unless noted.
+    fn assert_golden(segmenter: &Segmenter, golden: &[(&str, &[usize])]) {
+        for (text, expected) in golden.iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            assert_eq!(segmenter.segment(&utf16), *expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn khmer_test() {
+        const KM_STR: [u16; 27] = [
+            0x1797, 0x17b6, 0x179f, 0x17b6, 0x1781, 0x17d2, 0x1798, 0x17c2, 0x179a, 0x1797, 0x17b6,
+            0x179f, 0x17b6, 0x1781, 0x17d2, 0x1798, 0x17c2, 0x179a, 0x1797, 0x17b6, 0x179f, 0x17b6,
+            0x1781, 0x17d2, 0x1798, 0x17c2, 0x179a,
+        ];
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA)).with_strategy(Strategy::Khmer);
+        assert_eq!(segmenter.segment(&KM_STR), [9, 18, 27]);
+
+        assert_golden(
+            &segmenter,
+            &[
+                ("ខ្ញុំស្រឡាញ់អ្នក", &[5, 12, 16]),
+                ("សួស្តីពិភពលោក", &[6, 13]),
+                ("ព្រះរាជាណាចក្រកម្ពុជា", &[14, 21]),
+                ("ខ្ញុំចង់ទៅផ្សារ", &[5, 10, 15]),
+                ("សូមអរគុណច្រើន", &[3, 8, 13]),
+                ("រាជធានីភ្នំពេញ", &[7, 14]),
+                ("នេះគឺជាការសាកល្បង", &[3, 7, 17]),
+                ("ខ្ញុំរៀនភាសាអង់គ្លេសនៅសាលា", &[5, 8, 20, 22, 26]),
+                (
+                    "ប្រជាជនកម្ពុជាមានចំនួនប្រហែលដប់ប្រាំលាននាក់",
+                    &[7, 14, 17, 22, 28, 36, 39, 43],
+                ),
+                ("គាត់កំពុងអានសៀវភៅនៅក្នុងបណ្ណាល័យ", &[4, 9, 12, 17, 24, 32]),
+                ("ថ្ងៃនេះអាកាសធាតុល្អណាស់", &[7, 16, 19, 23]),
+            ],
+        );
+
+        // Greedy matching differs from ICU on these.
+        let golden: &[(&str, &[usize])] = &[
+            ("ទន្ទេញធម៌ះេវ", &[6, 11, 12]),
+            ("គាភអូរអំបិល", &[3, 11]),
+            ("អចលនកម្មអ្នកប", &[8, 11, 13]),
+            ("ដើរកអាឡូវវិរ", &[3, 4, 6, 9, 12]),
+            ("បកំរំដោះភាប", &[3, 8, 11]),
+            ("គ្រលកំណើនអថេរ", &[3, 6, 9, 13]),
+            ("រាំងរដូវប្រវត", &[3, 5, 7, 11, 13]),
+            ("ទិសក្រោមប", &[2, 9]),
+        ];
+        assert_golden(&segmenter, golden);
+        let forward = Segmenter::new(Dictionary::new(KHMER_DATA));
+        for (text, expected) in golden.iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            assert_ne!(forward.segment(&utf16), *expected);
+        }
+
+        // The engine only segments what the dictionary can hold, the same
+        // text the segmenter treats as unsafe.
+        let dictionary = Dictionary::new(KHMER_DATA);
+        for (first, last) in KHMER.word {
+            assert!((*first..=*last).all(|c| dictionary.contains_unit(c)));
+        }
+    }
+
+    #[test]
+    fn lao_test() {
+        const LO_STR: [u16; 21] = [
+            0x0e9e, 0x0eb2, 0x0eaa, 0x0eb2, 0x0ea5, 0x0eb2, 0x0ea7, 0x0e9e, 0x0eb2, 0x0eaa, 0x0eb2,
+            0x0ea5, 0x0eb2, 0x0ea7, 0x0e9e, 0x0eb2, 0x0eaa, 0x0eb2, 0x0ea5, 0x0eb2, 0x0ea7,
+        ];
+        let segmenter = Segmenter::new(Dictionary::new(LAO_DATA)).with_strategy(Strategy::Lao);
+        assert_eq!(segmenter.segment(&LO_STR), [4, 7, 11, 14, 18, 21]);
+
+        assert_golden(
+            &segmenter,
+            &[
+                ("ຂ້ອຍຮັກປະເທດລາວ", &[4, 7, 12, 15]),
+                ("ວຽງຈັນເປັນນະຄອນຫຼວງ", &[6, 10, 19]),
+                ("ມື້ນີ້ອາກາດດີ", &[6, 11, 13]),
+                ("ພວກເຮົາໄປຕະຫຼາດ", &[3, 7, 9, 15]),
+            ],
+        );
+
+        // Greedy matching differs from ICU on these.
+        let golden: &[(&str, &[usize])] = &[
+            ("ໂຢຊໃບປິວການ", &[3, 8, 11]),
+            ("ກະທິງມາລີປະເ", &[5, 7, 9, 12]),
+            ("ສະຫຼຮອຍໄໝ້ກີຣີ", &[2, 4, 7, 10, 14]),
+            ("ສົມເພດອິດທິປ", &[3, 6, 11, 12]),
+            ("ໂຢດໂການຄວບຄຸມ", &[3, 7, 13]),
+            ("ເຄືອບາງກອກ", &[4, 10]),
+        ];
+        assert_golden(&segmenter, golden);
+        let forward = Segmenter::new(Dictionary::new(LAO_DATA));
+        for (text, expected) in golden.iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            assert_ne!(forward.segment(&utf16), *expected);
+        }
+
+        // ICU's Lao engine has no suffixes, so these are this crate's own
+        // results: the repetition mark and the ellipsis stay with the
+        // preceding word, but only the first of several. ICU 72 gives
+        // [4, 7, 12, 16, 17] for the second text.
+        let suffixes: &[(&str, &[usize])] = &[
+            ("ເດັກນ້ອຍໆຫຼິ້ນ", &[4, 9, 14]),
+            ("ຂ້ອຍຮັກເຈົ້າຫຼາຍໆ", &[4, 7, 12, 17]),
+            ("ລາວຯລາວ", &[4, 7]),
+            ("ຄົນໆໆໄປ", &[4, 5, 7]),
+        ];
+        assert_golden(&segmenter, suffixes);
+    }
+
+    #[test]
+    fn thai_test() {
+        let data = build_words(
+            DictionaryBuilder::bytes(0x0e00),
+            &[
+                "ไป",
+                "โรง",
+                "โรงเรียน",
+                "เรียน",
+                "แมว",
+                "ดี",
+                "กิน",
+                "ข้าว",
+                "กรุงเทพ",
+            ],
+        );
+        let segmenter = Segmenter::new(Dictionary::new(&data)).with_strategy(Strategy::Thai);
+        let forward = Segmenter::new(Dictionary::new(&data));
+        let cases: &[(&str, &[usize], &[usize])] = &[
+            ("ไปโรงเรียน", &[2, 10], &[2, 10]),
+            // MAIYAMOK repeats the preceding word.
+            ("แมวดีๆกินข้าว", &[3, 6, 9, 13], &[3, 5, 6, 9, 13]),
+            // PAIYANNOI abbreviates it.
+            ("กรุงเทพฯไป", &[8, 10], &[7, 8, 10]),
+            // Unknown text is combined with the short word before it, up to
+            // where a dictionary word starts.
+            ("ไปสนุกกินข้าว", &[6, 9, 13], &[2, 3, 5, 6, 9, 13]),
+        ];
+        for (text, expected, greedy) in cases.iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            assert_eq!(segmenter.segment(&utf16), *expected, "{}", text);
+            assert_eq!(forward.segment(&utf16), *greedy, "{}", text);
+        }
+    }
+
+    #[test]
+    fn burmese_test() {
+        let data = build_words(
+            DictionaryBuilder::bytes(0x1000),
+            &["မ", "မြန်မာ", "စာ", "သ", "သင်", "ကျောင်း"],
+        );
+        let segmenter = Segmenter::new(Dictionary::new(&data)).with_strategy(Strategy::Burmese);
+        let forward = Segmenter::new(Dictionary::new(&data));
+        let cases: &[(&str, &[usize], &[usize])] = &[
+            ("မြန်မာစာ", &[6, 8], &[6, 8]),
+            ("ကျောင်းသင်", &[7, 10], &[7, 10]),
+            // The medial YA after မ keeps the unknown syllable together.
+            ("မျက်စာ", &[4, 6], &[2, 4, 6]),
+        ];
+        for (text, expected, greedy) in cases.iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            assert_eq!(segmenter.segment(&utf16), *expected, "{}", text);
+            assert_eq!(forward.segment(&utf16), *greedy, "{}", text);
+        }
+
+        // Myanmar Extended-B digits aren't joined to the word before them,
+        // even by a dictionary that can hold them.
+        let data = build_words(DictionaryBuilder::uchars(), &["စာ"]);
+        let segmenter = Segmenter::new(Dictionary::new(&data)).with_strategy(Strategy::Burmese);
+        let utf16: Vec<u16> = "စာ\u{a9f1}\u{a9f2}".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16), [2, 3, 4]);
+    }
+}
@@ -0,0 +1,29 @@
+use core::fmt;
+
+/// Errors reported by dictionary lookups, building and segmentation modes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The operation needs per-word values, but the dictionary header does
+    /// not set `TRIE_HAS_VALUES`.
+    NoValues,
+    /// A dictionary can't be built without words.
+    Empty,
+    /// The character can't be stored in a bytes trie with the dictionary's
+    /// offset transform.
+    OutOfRange(u16),
+    /// The data is not a dictionary in ICU's `.dict` format.
+    InvalidDictionary(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoValues => write!(f, "dictionary does not carry values"),
+            Error::Empty => write!(f, "dictionary has no words"),
+            Error::OutOfRange(c) => write!(f, "U+{:04X} is out of the dictionary range", c),
+            Error::InvalidDictionary(reason) => write!(f, "invalid dictionary: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
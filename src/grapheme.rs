@@ -0,0 +1,207 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+// Returns whether `pos` is an extended grapheme cluster boundary of `input`.
+// Nothing is allocated.
+pub(crate) fn is_grapheme_boundary(input: &[u16], pos: usize) -> bool {
+    if pos == 0 || pos >= input.len() {
+        return true;
+    }
+    let (previous, unit) = (input[pos - 1], input[pos]);
+    if (0xd800..0xdc00).contains(&previous) && (0xdc00..0xe000).contains(&unit) {
+        return false;
+    }
+    let is_surrogate = |unit: u16| (0xd800..0xe000).contains(&unit);
+    if !is_surrogate(previous) && !is_surrogate(unit) {
+        let previous = char::from_u32(u32::from(previous)).unwrap();
+        let c = char::from_u32(u32::from(unit)).unwrap();
+        if is_simple_boundary(previous, c) {
+            return true;
+        }
+    }
+
+    let start = previous_char(input, pos, 0);
+    let end = next_char(input, pos, input.len());
+    let mut chunk_buffer = [0; 12];
+    let chunk = decode(&input[start..end], &mut chunk_buffer);
+    let before = decode(&input[start..pos], &mut [0; 6]).len();
+
+    // The cursor works on UTF-8 offsets of a text we never decode in full.
+    // Put the chunk far enough from 0 that any context before it still has
+    // room, and keep offset 0 for a newline standing in for the start of
+    // the text. A newline ends the emoji and regional indicator sequences
+    // that the cursor looks back over, just like the start of the text does.
+    let chunk_start = 3 * start + 1;
+    let len = if end == input.len() {
+        chunk_start + chunk.len()
+    } else {
+        chunk_start + chunk.len() + 1
+    };
+    let mut cursor = GraphemeCursor::new(chunk_start + before, len, true);
+    let mut context_start = start;
+    let mut context_buffer = [0; 3 * 17];
+    loop {
+        match cursor.is_boundary(chunk, chunk_start) {
+            Ok(result) => return result,
+            Err(GraphemeIncomplete::PreContext(offset)) => {
+                if context_start == 0 {
+                    cursor.provide_context("\n", offset - 1);
+                    continue;
+                }
+                let mut from = context_start.saturating_sub(16);
+                if from > 0 && (0xdc00..0xe000).contains(&input[from]) {
+                    from -= 1;
+                }
+                let context = decode(&input[from..context_start], &mut context_buffer);
+                cursor.provide_context(context, offset - context.len());
+                context_start = from;
+            }
+            // The chunk always holds the characters on both sides of `pos`.
+            Err(_) => return true,
+        }
+    }
+}
+
+// Returns the offset after the grapheme cluster at `pos`, but not past `end`.
+pub(crate) fn next_cluster(input: &[u16], pos: usize, end: usize) -> usize {
+    let mut pos = next_char(input, pos, end);
+    while pos < end && !is_grapheme_boundary(input, pos) {
+        pos = next_char(input, pos, end);
+    }
+    pos
+}
+
+// Returns the offset before the grapheme cluster ending at `pos`, but not before `start`.
+pub(crate) fn previous_cluster(input: &[u16], pos: usize, start: usize) -> usize {
+    let mut pos = previous_char(input, pos, start);
+    while pos > start && !is_grapheme_boundary(input, pos) {
+        pos = previous_char(input, pos, start);
+    }
+    pos
+}
+
+// Returns the offset after the character at `pos`, keeping surrogate pairs together.
+pub(crate) fn next_char(input: &[u16], pos: usize, end: usize) -> usize {
+    if pos + 1 < end
+        && (0xd800..0xdc00).contains(&input[pos])
+        && (0xdc00..0xe000).contains(&input[pos + 1])
+    {
+        pos + 2
+    } else {
+        pos + 1
+    }
+}
+
+// Returns the offset before the character ending at `pos`, keeping surrogate pairs together.
+pub(crate) fn previous_char(input: &[u16], pos: usize, start: usize) -> usize {
+    if pos >= start + 2
+        && (0xdc00..0xe000).contains(&input[pos - 1])
+        && (0xd800..0xdc00).contains(&input[pos - 2])
+    {
+        pos - 2
+    } else {
+        pos - 1
+    }
+}
+
+// Returns whether UAX #29 breaks between the BMP characters `previous` and
+// `c` whatever comes before them. This is the case unless one of them is a
+// combining mark, a joiner, a Hangul jamo or syllable or another character
+// that can join a cluster, `previous` is a prepended concatenation mark, or
+// they are CR LF.
+fn is_simple_boundary(previous: char, c: char) -> bool {
+    let joins = |c: char| {
+        is_combining_mark(c)
+            || matches!(
+                c,
+                // ZWNJ and ZWJ, the Thai and Lao vowel AM, and the
+                // half-width katakana sound marks.
+                '\u{200c}' | '\u{200d}' | '\u{0e33}' | '\u{0eb3}' | '\u{ff9e}' | '\u{ff9f}'
+            )
+    };
+    let is_hangul = |c: char| {
+        matches!(
+            c,
+            '\u{1100}'..='\u{11ff}' | '\u{a960}'..='\u{a97f}' | '\u{ac00}'..='\u{d7ff}'
+        )
+    };
+    let is_prepend = matches!(
+        previous,
+        '\u{0600}'..='\u{0605}'
+            | '\u{06dd}'
+            | '\u{070f}'
+            | '\u{0890}'..='\u{0891}'
+            | '\u{08e2}'
+            | '\u{0d4e}'
+    );
+    !((previous == '\r' && c == '\n')
+        || joins(previous)
+        || joins(c)
+        || is_hangul(previous)
+        || is_hangul(c)
+        || is_prepend)
+}
+
+// Decodes UTF-16 into `buffer`, replacing unpaired surrogates by U+FFFD.
+// `buffer` needs three bytes per unit.
+fn decode<'b>(units: &[u16], buffer: &'b mut [u8]) -> &'b str {
+    let mut length = 0;
+    for c in core::char::decode_utf16(units.iter().cloned()) {
+        length += c
+            .unwrap_or('\u{fffd}')
+            .encode_utf8(&mut buffer[length..])
+            .len();
+    }
+    core::str::from_utf8(&buffer[..length]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grapheme::*;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    #[test]
+    fn grapheme_boundary_test() {
+        // ក្សា is one cluster: the coeng joins the consonants.
+        let utf16: Vec<u16> = "ក្សា".encode_utf16().collect();
+        let boundaries: Vec<bool> = (0..=utf16.len())
+            .map(|pos| is_grapheme_boundary(&utf16, pos))
+            .collect();
+        assert_eq!(boundaries, [true, false, false, false, true]);
+
+        // Combining marks and surrogate pairs.
+        let utf16: Vec<u16> = "e\u{301}𠮷".encode_utf16().collect();
+        assert!(!is_grapheme_boundary(&utf16, 1));
+        assert!(is_grapheme_boundary(&utf16, 2));
+        assert!(!is_grapheme_boundary(&utf16, 3));
+        assert_eq!(next_cluster(&utf16, 0, utf16.len()), 2);
+        assert_eq!(previous_cluster(&utf16, 4, 0), 2);
+
+        // Regional indicators pair up from the start of the text, which is
+        // further back than the first look.
+        let flags: Vec<u16> = "🇯🇵🇯🇵🇯🇵🇯🇵🇯🇵🇯🇵".encode_utf16().collect();
+        let boundaries: Vec<usize> = (1..flags.len())
+            .filter(|pos| is_grapheme_boundary(&flags, *pos))
+            .collect();
+        assert_eq!(boundaries, [4, 8, 12, 16, 20]);
+
+        // An emoji ZWJ sequence is one cluster.
+        let utf16: Vec<u16> = "👩\u{200d}👩".encode_utf16().collect();
+        assert_eq!(next_cluster(&utf16, 0, utf16.len()), utf16.len());
+
+        // Text where the quick check can't decide, matching
+        // unicode-segmentation on the whole text.
+        let text = "a\r\nกำ ລຳ 한국어 ｶﾞ क्षि ؀a ❤\u{200d}🔥 e\u{301}\u{200c}x ភាសា\n";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let mut expected = Vec::new();
+        let mut offset = 0;
+        for cluster in text.graphemes(true) {
+            expected.push(offset);
+            offset += cluster.encode_utf16().count();
+        }
+        let boundaries: Vec<usize> = (0..utf16.len())
+            .filter(|pos| is_grapheme_boundary(&utf16, *pos))
+            .collect();
+        assert_eq!(boundaries, expected);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::segmenter::*;
+use core::ops::Range;
+
+impl<'a> Segmenter<'a> {
+    /// Updates the `boundaries` of a text after `edit`, a range of it, was
+    /// replaced with `inserted` code units. `input` is the text after the
+    /// edit.
+    ///
+    /// Only the text from the last safe position before the edit is
+    /// segmented again, up to the first safe position after it where the
+    /// boundaries re-synchronize with the old ones. The result is the same
+    /// as `segment` on the whole of `input`.
+    pub fn resegment(
+        &self,
+        input: &[u16],
+        boundaries: &[usize],
+        edit: Range<usize>,
+        inserted: usize,
+    ) -> Vec<usize> {
+        let edit_end = edit.start + inserted;
+        // Old offsets after the edit are new offsets minus `inserted` plus
+        // `removed`.
+        let removed = edit.end - edit.start;
+        let start = self.safe_position_before(input, edit.start.saturating_sub(1));
+        let kept = boundaries.partition_point(|boundary| *boundary <= start);
+        let mut result = boundaries[..kept].to_vec();
+
+        let mut pos = start;
+        while pos < input.len() {
+            let end = self.safe_position_after(input, pos + 1);
+            result.extend(
+                self.segment(&input[pos..end])
+                    .into_iter()
+                    .map(|boundary| pos + boundary),
+            );
+            pos = end;
+            // Past the edit, the text after a safe position is the old one.
+            if pos > edit_end && pos < input.len() {
+                let old = pos - inserted + removed;
+                if let Ok(i) = boundaries.binary_search(&old) {
+                    result.extend(
+                        boundaries[i + 1..]
+                            .iter()
+                            .map(|boundary| boundary + inserted - removed),
+                    );
+                    break;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::incremental::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+
+    #[test]
+    fn resegment_test() {
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA)).with_strategy(Strategy::Khmer);
+        let text: Vec<u16> = "ខ្ញុំស្រឡាញ់អ្នក សួស្តីពិភពលោក ខ្ញុំចង់ទៅផ្សារ".encode_utf16().collect();
+        let edits: [(Range<usize>, &str); 6] = [
+            (0..0, "ភាសាខ្មែរ"),
+            (5..12, ""),
+            (16..17, ""),
+            (20..20, " "),
+            (17..23, "ព្រះរាជាណាចក្រកម្ពុជា"),
+            (text.len()..text.len(), "សូមអរគុណច្រើន"),
+        ];
+        let boundaries = segmenter.segment(&text);
+        for (range, replacement) in edits.iter() {
+            let mut input = text[..range.start].to_vec();
+            input.extend(replacement.encode_utf16());
+            input.extend_from_slice(&text[range.end..]);
+            let inserted = input.len() + range.len() - text.len();
+            assert_eq!(
+                segmenter.resegment(&input, &boundaries, range.clone(), inserted),
+                segmenter.segment(&input)
+            );
+        }
+
+        // Boundaries after the next safe position are copied.
+        let segmenter = Segmenter::new(Dictionary::new(LAO_DATA));
+        let text: Vec<u16> = "ພາສາລາວ ພາສາລາວ".encode_utf16().collect();
+        let boundaries = [4, 7, 8, 12, 15];
+        assert_eq!(segmenter.segment(&text), boundaries);
+        let input: Vec<u16> = "ລາວລາວ ພາສາລາວ".encode_utf16().collect();
+        assert_eq!(
+            segmenter.resegment(&input, &boundaries, 0..4, 3),
+            [3, 6, 7, 11, 14]
+        );
+        // Stale old boundaries show that they were not recomputed.
+        assert_eq!(
+            segmenter.resegment(&input, &[4, 7, 8, 9, 15], 0..4, 3),
+            [3, 6, 7, 8, 14]
+        );
+    }
+}
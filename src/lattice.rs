@@ -0,0 +1,305 @@
+use crate::dictionary::*;
+use crate::error::*;
+use crate::grapheme::*;
+use crate::normalization::*;
+use crate::segmenter::*;
+use core::fmt;
+
+// The cost of an unknown character, the same as ICU's CJK break engine.
+const UNKNOWN_COST: i64 = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeKind {
+    /// A dictionary word.
+    Word,
+    /// A grapheme cluster that isn't a dictionary word by itself.
+    Unknown,
+}
+
+/// How `Lattice::n_best` scores a segmentation. Lower is better.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ranking {
+    /// The sum of the dictionary values of the words, as in cjdict. Unknown
+    /// characters cost 255 each.
+    Cost,
+    /// The number of segments.
+    WordCount,
+}
+
+/// A segment candidate that ends at `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Edge {
+    pub end: usize,
+    /// The dictionary value of the word, if the dictionary has values.
+    pub value: Option<i32>,
+    pub kind: EdgeKind,
+}
+
+/// Every dictionary match in a text that starts and ends on a grapheme
+/// cluster boundary, plus an unknown edge over the cluster at each boundary
+/// that has no word of that length. Offsets inside a cluster have no edges.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lattice {
+    start: usize,
+    // The edges starting at each offset from `start`, shortest first.
+    edges: Vec<Vec<Edge>>,
+}
+
+impl Lattice {
+    // Builds the lattice of input[start..end].
+    pub(crate) fn new(dictionary: &Dictionary, input: &[u16], start: usize, end: usize) -> Self {
+        let mut lengths = Vec::new();
+        let edges = (start..end)
+            .map(|pos| {
+                let mut edges = Vec::new();
+                edges_into(dictionary, input, pos, end, &mut lengths, &mut edges);
+                edges
+            })
+            .collect();
+        Self { start, edges }
+    }
+
+    // Maps a lattice of normalized text back to the text it came from, see
+    // `normalize`. Edges that start or end inside a cluster that normalized
+    // to several characters are dropped, and such a cluster gets an unknown
+    // edge over it unless a word covers exactly it.
+    pub(crate) fn to_original(&self, offsets: &[(usize, usize)]) -> Self {
+        let length = offsets.last().map_or(0, |offset| offset.1);
+        let mut edges: Vec<Vec<Edge>> = vec![Vec::new(); length];
+        for (start, edge) in self.edges() {
+            let start = original_offset(start, offsets);
+            let end = original_offset(edge.end, offsets);
+            if let (Some(start), Some(end)) = (start, end) {
+                edges[start].push(Edge { end, ..*edge });
+            }
+        }
+        let mut start = 0;
+        for (_, end) in offsets.iter().cloned() {
+            let cluster_edges = &mut edges[start];
+            if !cluster_edges.iter().any(|edge| edge.end == end) {
+                let index = cluster_edges.iter().take_while(|e| e.end < end).count();
+                let edge = Edge {
+                    end,
+                    value: None,
+                    kind: EdgeKind::Unknown,
+                };
+                cluster_edges.insert(index, edge);
+            }
+            start = end;
+        }
+        Self { start: 0, edges }
+    }
+
+    /// Returns the offset of the first position.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the offset after the last position.
+    pub fn end(&self) -> usize {
+        self.start + self.edges.len()
+    }
+
+    /// Returns the edges starting at `offset`, shortest first.
+    pub fn edges_from(&self, offset: usize) -> &[Edge] {
+        if offset < self.start || offset >= self.end() {
+            return &[];
+        }
+        &self.edges[offset - self.start]
+    }
+
+    /// Returns all edges with their start offsets.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, &Edge)> + '_ {
+        self.edges
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, edges)| edges.iter().map(move |edge| (self.start + i, edge)))
+    }
+
+    /// Returns up to `n` segmentations of the lattice as the end offsets of
+    /// their segments, with their scores, best first. Equal scores prefer
+    /// longer segments at the start.
+    ///
+    /// Ranking by `Ranking::Cost` needs a dictionary with values.
+    pub fn n_best(&self, n: usize, ranking: Ranking) -> Result<Vec<(Vec<usize>, i64)>, Error> {
+        let cost = |edge: &Edge| match (ranking, edge.kind, edge.value) {
+            (Ranking::WordCount, _, _) => Ok(1),
+            (Ranking::Cost, EdgeKind::Unknown, _) => Ok(UNKNOWN_COST),
+            (Ranking::Cost, EdgeKind::Word, Some(value)) => Ok(i64::from(value)),
+            (Ranking::Cost, EdgeKind::Word, None) => Err(Error::NoValues),
+        };
+        // The best paths from each position to the end:
+        // (score, end of the first segment, index of the rest at that end).
+        let length = self.edges.len();
+        let mut best: Vec<Vec<(i64, usize, usize)>> = vec![Vec::new(); length + 1];
+        best[length].push((0, length, 0));
+        for i in (0..length).rev() {
+            let mut paths = Vec::new();
+            for edge in self.edges[i].iter().rev() {
+                let cost = cost(edge)?;
+                let to = edge.end - self.start;
+                for (k, rest) in best[to].iter().enumerate() {
+                    paths.push((cost + rest.0, to, k));
+                }
+            }
+            // The sort is stable, so longer first segments win ties.
+            paths.sort_by_key(|path| path.0);
+            paths.truncate(n);
+            best[i] = paths;
+        }
+
+        Ok((0..best[0].len())
+            .map(|k| {
+                let score = best[0][k].0;
+                let mut boundaries = Vec::new();
+                let (mut i, mut k) = (0, k);
+                while i < length {
+                    let (_, to, rest) = best[i][k];
+                    boundaries.push(self.start + to);
+                    i = to;
+                    k = rest;
+                }
+                (boundaries, score)
+            })
+            .collect())
+    }
+}
+
+// Replaces `edges` with the edges starting at `pos` of input[..end], shortest
+// first, using `lengths` for the dictionary matches. Offsets inside a
+// grapheme cluster have none.
+pub(crate) fn edges_into(
+    dictionary: &Dictionary,
+    input: &[u16],
+    pos: usize,
+    end: usize,
+    lengths: &mut Vec<(usize, Option<i32>)>,
+    edges: &mut Vec<Edge>,
+) {
+    edges.clear();
+    if !is_grapheme_boundary(input, pos) {
+        return;
+    }
+    matches_into(dictionary, input[pos..end].iter(), lengths);
+    edges.extend(
+        lengths
+            .iter()
+            .filter(|(length, _)| is_grapheme_boundary(input, pos + length))
+            .map(|(length, value)| Edge {
+                end: pos + length,
+                value: *value,
+                kind: EdgeKind::Word,
+            }),
+    );
+    let end = next_cluster(input, pos, end);
+    if !edges.iter().any(|edge| edge.end == end) {
+        let edge = Edge {
+            end,
+            value: None,
+            kind: EdgeKind::Unknown,
+        };
+        let index = edges.iter().take_while(|e| e.end < end).count();
+        edges.insert(index, edge);
+    }
+}
+
+/// Writes one edge per line as `start`, `end`, `word` or `unknown`, and the
+/// value (`-` if none), separated by tabs.
+impl fmt::Display for Lattice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (start, edge) in self.edges() {
+            let kind = match edge.kind {
+                EdgeKind::Word => "word",
+                EdgeKind::Unknown => "unknown",
+            };
+            match edge.value {
+                Some(value) => writeln!(f, "{}\t{}\t{}\t{}", start, edge.end, kind, value)?,
+                None => writeln!(f, "{}\t{}\t{}\t-", start, edge.end, kind)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary_builder::*;
+    use crate::lattice::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn lattice_test() {
+        let utf16: Vec<u16> = "焼肉定食".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let lattice = segmenter.lattice(&utf16);
+        assert_eq!((lattice.start(), lattice.end()), (0, 4));
+        let ends: Vec<usize> = lattice.edges_from(0).iter().map(|e| e.end).collect();
+        assert!(ends.contains(&2));
+        let edge = lattice.edges_from(0).iter().find(|e| e.end == 2).unwrap();
+        assert_eq!(edge.kind, EdgeKind::Word);
+        assert_eq!(edge.value, Some(88));
+        assert!(lattice.edges_from(4).is_empty());
+        assert!(lattice.to_string().contains("0\t2\tword\t88\n"));
+
+        let utf16: Vec<u16> = "aភាសា".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        let lattice = segmenter.lattice(&utf16);
+        assert_eq!(
+            lattice.edges_from(0),
+            [Edge {
+                end: 1,
+                value: None,
+                kind: EdgeKind::Unknown
+            }]
+        );
+        let edge = lattice.edges_from(1).last().unwrap();
+        assert_eq!((edge.end, edge.value, edge.kind), (5, None, EdgeKind::Word));
+        assert!(lattice.to_string().starts_with("0\t1\tunknown\t-\n"));
+
+        // Edges start only at grapheme cluster boundaries.
+        for text in ["ក្សា", "𠮷野", "aភាសា"].iter() {
+            let utf16: Vec<u16> = text.encode_utf16().collect();
+            let lattice = segmenter.lattice(&utf16);
+            for (start, edge) in lattice.edges() {
+                assert!(is_grapheme_boundary(&utf16, start));
+                assert!(is_grapheme_boundary(&utf16, edge.end));
+            }
+        }
+        let utf16: Vec<u16> = "ក្សា".encode_utf16().collect();
+        assert_eq!(segmenter.lattice(&utf16).edges().count(), 1);
+    }
+
+    #[test]
+    fn n_best_test() {
+        let data = build_words(DictionaryBuilder::uchars(), &["a", "ab", "abc", "cd", "d"]);
+        let segmenter = Segmenter::new(Dictionary::new(&data));
+        let utf16: Vec<u16> = "abcd".encode_utf16().collect();
+        assert_eq!(
+            segmenter.n_best(&utf16, 3, Ranking::WordCount),
+            Ok(vec![(vec![3, 4], 2), (vec![2, 4], 2), (vec![2, 3, 4], 3)])
+        );
+        assert_eq!(segmenter.n_best(&utf16, 0, Ranking::WordCount), Ok(vec![]));
+        assert_eq!(
+            segmenter.n_best(&utf16, 1, Ranking::Cost),
+            Err(Error::NoValues)
+        );
+
+        let utf16: Vec<u16> = "焼肉定食".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let n_best = segmenter.n_best(&utf16, 3, Ranking::Cost).unwrap();
+        assert_eq!(n_best.len(), 3);
+        assert_eq!(n_best[0].0, [2, 4]);
+        assert!(n_best.windows(2).all(|w| w[0].1 <= w[1].1));
+        for (boundaries, score) in n_best.iter() {
+            let mut cost = 0;
+            let mut start = 0;
+            for end in boundaries.iter() {
+                let value = segmenter.dictionary().value(&utf16[start..*end]).unwrap();
+                cost += value.map_or(255, i64::from);
+                start = *end;
+            }
+            assert_eq!(cost, *score);
+        }
+    }
+}
@@ -1,6 +1,37 @@
+mod batch;
 mod bytes_trie;
+mod dictionary;
+mod dictionary_builder;
 mod dictionary_iterator;
+mod engine;
+mod error;
+mod grapheme;
+mod incremental;
+mod lattice;
+mod line_breaker;
+mod multi_segmenter;
+mod navigation;
+mod normalization;
+mod parallel;
+mod script;
+mod segmenter;
+mod separator;
+mod stream;
 mod trie;
 mod uchars_trie;
+mod word_boundaries;
 
+pub use crate::dictionary::Dictionary;
+pub use crate::dictionary_builder::DictionaryBuilder;
 pub use crate::dictionary_iterator::DictionaryIterator;
+pub use crate::error::Error;
+pub use crate::lattice::{Edge, EdgeKind, Lattice, Ranking};
+pub use crate::line_breaker::{LineBreak, LineBreaker};
+pub use crate::multi_segmenter::MultiSegmenter;
+pub use crate::navigation::SegmentKind;
+pub use crate::normalization::Normalization;
+pub use crate::script::Script;
+pub use crate::segmenter::{Segmenter, Strategy, TieBreak};
+pub use crate::separator::strip_separator;
+pub use crate::stream::{Encoding, ReadBoundaries, StreamSegmenter};
+pub use crate::word_boundaries::WordBoundaries;
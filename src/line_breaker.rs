@@ -0,0 +1,130 @@
+use crate::multi_segmenter::*;
+use crate::script::*;
+use unicode_linebreak::{break_property, linebreaks, BreakClass, BreakOpportunity};
+
+/// A line break opportunity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineBreak {
+    /// The line has to end here, such as after a newline or at the end of
+    /// the text.
+    Mandatory,
+    /// The line may end here.
+    Allowed,
+}
+
+/// Finds line break opportunities by the UAX #14 rules, with word
+/// boundaries from dictionaries inside runs of Southeast Asian scripts
+/// (Line_Break=SA), like ICU's line break iterator.
+#[derive(Clone)]
+pub struct LineBreaker<'a> {
+    segmenter: MultiSegmenter<'a>,
+    word_based_cj: bool,
+}
+
+impl<'a> LineBreaker<'a> {
+    /// Uses the word boundaries of `segmenter` for SA runs. Runs of a script
+    /// without a segmenter have no break opportunities inside them.
+    pub fn new(segmenter: MultiSegmenter<'a>) -> Self {
+        Self {
+            segmenter,
+            word_based_cj: false,
+        }
+    }
+
+    /// Allows breaks between Han and kana characters only at word
+    /// boundaries, keeping words on one line. Needs a segmenter for
+    /// `Script::Cj`.
+    pub fn with_word_based_cj(mut self, word_based_cj: bool) -> Self {
+        self.word_based_cj = word_based_cj;
+        self
+    }
+
+    /// Returns the offset and type of each line break opportunity in
+    /// `input`, ending with a mandatory break at the end.
+    pub fn line_breaks(&self, input: &[u16]) -> Vec<(usize, LineBreak)> {
+        let (text, offsets) = decode_with_offsets(input, 0, input.len());
+        let mut breaks: Vec<(usize, LineBreak)> = linebreaks(&text)
+            .map(|(index, opportunity)| {
+                let kind = match opportunity {
+                    BreakOpportunity::Mandatory => LineBreak::Mandatory,
+                    BreakOpportunity::Allowed => LineBreak::Allowed,
+                };
+                (utf16_offset(&offsets, index), kind)
+            })
+            .collect();
+
+        let words = self.segmenter.segment(input);
+        // Whether the characters on both sides of `pos` satisfy `f`.
+        let between = |pos: usize, f: &dyn Fn(char) -> bool| {
+            pos > 0
+                && pos < input.len()
+                && f(char_at(input, pos).0)
+                && f(previous_char_at(input, pos))
+        };
+        // SA characters of a script without a segmenter get no breaks.
+        let is_segmented_sa = |c: char| {
+            break_property(c as u32) == BreakClass::ComplexContext
+                && dictionary_script(c).is_some_and(|s| self.segmenter.segmenter(s).is_some())
+        };
+        for word in words.iter() {
+            if between(*word, &is_segmented_sa) {
+                if let Err(i) = breaks.binary_search_by_key(word, |b| b.0) {
+                    breaks.insert(i, (*word, LineBreak::Allowed));
+                }
+            }
+        }
+        if self.word_based_cj {
+            let is_cj = |c: char| dictionary_script(c) == Some(Script::Cj);
+            breaks.retain(|(pos, kind)| {
+                *kind == LineBreak::Mandatory
+                    || !between(*pos, &is_cj)
+                    || words.binary_search(pos).is_ok()
+            });
+        }
+        breaks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::line_breaker::*;
+    use crate::segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn line_break_test() {
+        let segmenter = MultiSegmenter::new()
+            .with_segmenter(Script::Khmer, Segmenter::new(Dictionary::new(KHMER_DATA)))
+            .with_segmenter(Script::Cj, Segmenter::new(Dictionary::new(CJ_DATA)));
+        let breaker = LineBreaker::new(segmenter);
+
+        // The break at 9 is from the dictionary; UAX #14 alone has none
+        // inside the Khmer run.
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ (abc)\nxyz".encode_utf16().collect();
+        assert_eq!(
+            breaker.line_breaks(&utf16),
+            [
+                (9, LineBreak::Allowed),
+                (19, LineBreak::Allowed),
+                (25, LineBreak::Mandatory),
+                (28, LineBreak::Mandatory)
+            ]
+        );
+
+        // Ideographs can break anywhere, but not before a closing
+        // punctuation mark.
+        let utf16: Vec<u16> = "焼肉定食を食べる。".encode_utf16().collect();
+        let breaks: Vec<usize> = breaker.line_breaks(&utf16).iter().map(|b| b.0).collect();
+        assert_eq!(breaks, [1, 2, 3, 4, 5, 6, 7, 9]);
+        let breaker = breaker.with_word_based_cj(true);
+        let breaks: Vec<usize> = breaker.line_breaks(&utf16).iter().map(|b| b.0).collect();
+        assert_eq!(breaks, [2, 4, 5, 9]);
+
+        // There is no Thai segmenter, so the Thai run doesn't break.
+        let utf16: Vec<u16> = "ราคาบาท ភាសាខ្មែរភាសាខ្មែរ".encode_utf16().collect();
+        let breaks: Vec<usize> = breaker.line_breaks(&utf16).iter().map(|b| b.0).collect();
+        assert_eq!(breaks, [8, 17, 26]);
+    }
+}
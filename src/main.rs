@@ -0,0 +1,289 @@
+use bytestrie::{Dictionary, MultiSegmenter, Script, SegmentKind, Segmenter, Strategy};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use unicode_segmentation::UnicodeSegmentation;
+
+const USAGE: &str = "\
+Usage: bytestrie [OPTIONS] [FILE]...
+
+Segments UTF-8 text from each FILE, or from standard input if there is none
+or FILE is -, one line at a time.
+
+Options:
+  -l, --language LANG     Use the bundled dictionary for LANG (km, lo, ja, zh)
+  -d, --dictionary PATH   Use the ICU .dict file at PATH
+  -s, --separator SEP     Put SEP between words (default: a space)
+      --sentences         Print one sentence per line
+      --json              Print a JSON object per line, with the UTF-8 byte
+                          offset of each segment from the start of the input
+  -h, --help              Print this help
+
+Without -l or -d, all bundled dictionaries are used.";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    Words,
+    Sentences,
+    Json,
+}
+
+struct Options {
+    languages: Vec<String>,
+    dictionaries: Vec<String>,
+    separator: String,
+    output: Output,
+    files: Vec<String>,
+}
+
+fn main() {
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("bytestrie: {}\n\n{}", message, USAGE);
+        process::exit(2);
+    });
+    if let Err(message) = run(&options) {
+        eprintln!("bytestrie: {}", message);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        languages: Vec::new(),
+        dictionaries: Vec::new(),
+        separator: " ".to_string(),
+        output: Output::Words,
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "-l" | "--language" => options.languages.push(value(&arg)?),
+            "-d" | "--dictionary" => options.dictionaries.push(value(&arg)?),
+            "-s" | "--separator" => options.separator = value(&arg)?,
+            "--sentences" => options.output = Output::Sentences,
+            "--json" => options.output = Output::Json,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-" => options.files.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => options.files.push(arg),
+        }
+    }
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut data = Vec::new();
+    for path in options.dictionaries.iter() {
+        data.push(std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    let mut languages = options.languages.clone();
+    if languages.is_empty() && data.is_empty() {
+        languages = vec!["km".to_string(), "lo".to_string(), "ja".to_string()];
+    }
+
+    let mut segmenter = MultiSegmenter::new();
+    for language in languages.iter() {
+        let bundled = Segmenter::for_locale(language);
+        if bundled.is_none() && !options.languages.is_empty() {
+            return Err(format!("no bundled dictionary for {}", language));
+        }
+        if let Some(bundled) = bundled {
+            let script = bundled.dictionary().script().unwrap();
+            segmenter = segmenter.with_segmenter(script, bundled);
+        }
+    }
+    for (path, data) in options.dictionaries.iter().zip(data.iter()) {
+        let dictionary = Dictionary::try_new(data).map_err(|e| format!("{}: {}", path, e))?;
+        let script = dictionary
+            .script()
+            .ok_or(format!("{}: unknown dictionary script", path))?;
+        let strategy = match script {
+            Script::Khmer => Strategy::Khmer,
+            Script::Lao => Strategy::Lao,
+            Script::Thai => Strategy::Thai,
+            Script::Myanmar => Strategy::Burmese,
+            Script::Cj => Strategy::Forward,
+        };
+        let dictionary_segmenter = Segmenter::new(dictionary).with_strategy(strategy);
+        segmenter = segmenter.with_segmenter(script, dictionary_segmenter);
+    }
+
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    let files = if options.files.is_empty() {
+        vec!["-".to_string()]
+    } else {
+        options.files.clone()
+    };
+    for file in files.iter() {
+        let result = if file == "-" {
+            segment_lines(&segmenter, options, io::stdin().lock(), &mut output)
+        } else {
+            let reader = File::open(file).map(BufReader::new);
+            reader.and_then(|reader| segment_lines(&segmenter, options, reader, &mut output))
+        };
+        result.map_err(|e| format!("{}: {}", file, e))?;
+    }
+    output.flush().map_err(|e| e.to_string())
+}
+
+// Segments `reader` one line at a time and writes the result to `output`.
+fn segment_lines(
+    segmenter: &MultiSegmenter,
+    options: &Options,
+    mut reader: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut line = String::new();
+    let mut offset = 0;
+    loop {
+        line.clear();
+        let length = reader.read_line(&mut line)?;
+        if length == 0 {
+            return Ok(());
+        }
+        let text = line.trim_end_matches(['\n', '\r']);
+        match options.output {
+            Output::Words => writeln!(
+                output,
+                "{}",
+                words(segmenter, text).join(&options.separator)
+            )?,
+            Output::Sentences => {
+                for sentence in text.split_sentence_bounds() {
+                    let words = words(segmenter, sentence.trim());
+                    if !words.is_empty() {
+                        writeln!(output, "{}", words.join(&options.separator))?;
+                    }
+                }
+            }
+            Output::Json => {
+                let segments: Vec<String> = segments(segmenter, text)
+                    .into_iter()
+                    .map(|(start, end, kind)| {
+                        format!(
+                            "{{\"start\":{},\"end\":{},\"text\":{},\"kind\":\"{}\"}}",
+                            offset + start,
+                            offset + end,
+                            json_string(&text[start..end]),
+                            match kind {
+                                SegmentKind::Word => "word",
+                                SegmentKind::Whitespace => "whitespace",
+                                SegmentKind::Punctuation => "punctuation",
+                            }
+                        )
+                    })
+                    .collect();
+                writeln!(output, "{{\"segments\":[{}]}}", segments.join(","))?;
+            }
+        }
+        offset += length;
+    }
+}
+
+// Returns the segments of `text` that aren't whitespace.
+fn words<'t>(segmenter: &MultiSegmenter, text: &'t str) -> Vec<&'t str> {
+    segments(segmenter, text)
+        .into_iter()
+        .filter(|(_, _, kind)| *kind != SegmentKind::Whitespace)
+        .map(|(start, end, _)| &text[start..end])
+        .collect()
+}
+
+// Returns the UTF-8 start and end offsets and the kind of each segment of
+// `text`.
+fn segments(segmenter: &MultiSegmenter, text: &str) -> Vec<(usize, usize, SegmentKind)> {
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    // The UTF-8 offset of each UTF-16 offset that starts a character.
+    let mut utf8_offsets = vec![0; utf16.len() + 1];
+    let mut utf16_offset = 0;
+    for (index, c) in text.char_indices() {
+        utf8_offsets[utf16_offset] = index;
+        utf16_offset += c.len_utf16();
+    }
+    utf8_offsets[utf16.len()] = text.len();
+
+    let mut start = 0;
+    segmenter
+        .segment_kinds(&utf16)
+        .into_iter()
+        .map(|(end, kind)| {
+            let segment = (utf8_offsets[start], utf8_offsets[end], kind);
+            start = end;
+            segment
+        })
+        .collect()
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn bad_dictionary_test() {
+        let options = |path: &str| Options {
+            languages: Vec::new(),
+            dictionaries: vec![path.to_string()],
+            separator: " ".to_string(),
+            output: Output::Words,
+            files: vec!["-".to_string()],
+        };
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        assert_eq!(
+            run(&options(manifest)).unwrap_err(),
+            format!("{}: invalid dictionary: not an ICU dictionary", manifest)
+        );
+
+        // A valid header with the trie cut off.
+        let path = std::env::temp_dir().join("bytestrie_bad_dictionary_test.dict");
+        std::fs::write(&path, &include_bytes!("../data/laodict.dict")[..200]).unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            run(&options(path)).unwrap_err(),
+            format!("{}: invalid dictionary: trie out of range", path)
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "cj")]
+    fn segments_test() {
+        let segmenter =
+            MultiSegmenter::new().with_segmenter(Script::Cj, Segmenter::for_locale("ja").unwrap());
+        let text = "焼肉 \"ok\"";
+        assert_eq!(
+            segments(&segmenter, text),
+            [
+                (0, 6, SegmentKind::Word),
+                (6, 7, SegmentKind::Whitespace),
+                (7, 8, SegmentKind::Punctuation),
+                (8, 10, SegmentKind::Word),
+                (10, 11, SegmentKind::Punctuation)
+            ]
+        );
+        assert_eq!(words(&segmenter, text), ["焼肉", "\"", "ok", "\""]);
+        assert_eq!(json_string("a\"b\\\n\u{1}"), "\"a\\\"b\\\\\\n\\u0001\"");
+    }
+}
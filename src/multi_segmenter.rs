@@ -0,0 +1,225 @@
+use crate::grapheme::*;
+use crate::script::*;
+use crate::segmenter::*;
+use unicode_normalization::char::is_combining_mark;
+use unicode_script::{Script as UScript, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Returns the character at `pos` and the offset after it. Unpaired
+// surrogates are U+FFFD.
+pub(crate) fn char_at(input: &[u16], pos: usize) -> (char, usize) {
+    let end = next_char(input, pos, input.len());
+    let c = core::char::decode_utf16(input[pos..end].iter().cloned())
+        .next()
+        .unwrap()
+        .unwrap_or('\u{fffd}');
+    (c, end)
+}
+
+// Returns the character ending at `pos`.
+pub(crate) fn previous_char_at(input: &[u16], pos: usize) -> char {
+    let start = previous_char(input, pos, 0);
+    char_at(input, start).0
+}
+
+/// Segments text mixing several scripts.
+///
+/// The text is split into runs by the Unicode Script property. Runs of a
+/// script with a segmenter are segmented by it, the way ICU's rule based
+/// break iterator hands them to its dictionary engines. Everything else,
+/// including runs of a script without a segmenter, follows the UAX #29 word
+/// boundary rules, so that `3.14` and `don't` are single segments.
+#[derive(Clone, Default)]
+pub struct MultiSegmenter<'a> {
+    segmenters: Vec<(Script, Segmenter<'a>)>,
+}
+
+impl<'a> MultiSegmenter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Segments runs of `script` with `segmenter`, replacing any segmenter
+    /// set before.
+    pub fn with_segmenter(mut self, script: Script, segmenter: Segmenter<'a>) -> Self {
+        self.segmenters.retain(|(s, _)| *s != script);
+        self.segmenters.push((script, segmenter));
+        self
+    }
+
+    /// Returns the segmenter used for `script`.
+    pub fn segmenter(&self, script: Script) -> Option<&Segmenter<'a>> {
+        self.segmenters
+            .iter()
+            .find(|(s, _)| *s == script)
+            .map(|(_, segmenter)| segmenter)
+    }
+
+    /// Returns the end offset of each segment of `input`.
+    pub fn segment(&self, input: &[u16]) -> Vec<usize> {
+        // The script of each character if it has a segmenter, or None for
+        // UAX #29.
+        let script = |c: char| dictionary_script(c).filter(|s| self.segmenter(*s).is_some());
+        let mut boundaries = Vec::new();
+        let mut pos = 0;
+        while pos < input.len() {
+            let (c, mut end) = char_at(input, pos);
+            let run_script = script(c);
+            let run_segmenter = run_script.and_then(|s| self.segmenter(s));
+            while end < input.len() {
+                let (c, next) = char_at(input, end);
+                // Combining marks join the run they follow, and so do the
+                // characters its dictionary ignores.
+                let joins = c.script() == UScript::Inherited
+                    || run_segmenter.is_some_and(|s| s.dictionary().is_ignorable(c));
+                if !joins && script(c) != run_script {
+                    break;
+                }
+                end = next;
+            }
+            match run_segmenter {
+                Some(segmenter) => boundaries.extend(
+                    segmenter
+                        .segment(&input[pos..end])
+                        .into_iter()
+                        .map(|boundary| pos + boundary),
+                ),
+                None => word_bounds(input, pos, end, &mut boundaries),
+            }
+            pos = end;
+        }
+        boundaries
+    }
+
+    // Returns whether segmenting the text before and after `pos` on their
+    // own gives the boundaries of the whole text, with one at `pos`. This
+    // holds where the script run changes, at the safe positions of a run's
+    // segmenter, and in UAX #29 text after a line break or before a word
+    // that follows whitespace.
+    pub(crate) fn is_safe(&self, input: &[u16], pos: usize) -> bool {
+        if pos == 0 || pos >= input.len() {
+            return true;
+        }
+        if !is_grapheme_boundary(input, pos) {
+            return false;
+        }
+        let (previous, c) = (previous_char_at(input, pos), char_at(input, pos).0);
+        // Characters that may join the run before them.
+        let joins = |c: char| {
+            c.script() == UScript::Inherited
+                || self
+                    .segmenters
+                    .iter()
+                    .any(|(_, segmenter)| segmenter.dictionary().is_ignorable(c))
+        };
+        if joins(previous) || joins(c) {
+            return false;
+        }
+        let script = |c: char| dictionary_script(c).filter(|s| self.segmenter(*s).is_some());
+        match (script(previous), script(c)) {
+            (previous_script, run_script) if previous_script != run_script => true,
+            (Some(run_script), _) => self.segmenter(run_script).unwrap().is_safe(input, pos),
+            (None, _) => {
+                (is_line_break(input[pos - 1]) && !(input[pos - 1] == 0x0d && input[pos] == 0x0a))
+                    || (previous.is_whitespace() && c.is_alphanumeric() && !is_combining_mark(c))
+            }
+        }
+    }
+
+    // Returns the last safe position at or before `pos`.
+    pub(crate) fn safe_position_before(&self, input: &[u16], pos: usize) -> usize {
+        (0..=pos).rev().find(|p| self.is_safe(input, *p)).unwrap()
+    }
+
+    // Returns the first safe position at or after `pos`.
+    pub(crate) fn safe_position_after(&self, input: &[u16], pos: usize) -> usize {
+        (pos..input.len())
+            .find(|p| self.is_safe(input, *p))
+            .unwrap_or(input.len())
+    }
+}
+
+// Appends the UAX #29 word boundaries in start+1..=end to `boundaries`.
+fn word_bounds(input: &[u16], start: usize, end: usize, boundaries: &mut Vec<usize>) {
+    let (text, offsets) = decode_with_offsets(input, start, end);
+    for (index, word) in text.split_word_bound_indices() {
+        boundaries.push(utf16_offset(&offsets, index + word.len()));
+    }
+}
+
+// Decodes input[start..end], returning the text and the UTF-16 offset of
+// each UTF-8 character offset, including the end.
+pub(crate) fn decode_with_offsets(
+    input: &[u16],
+    start: usize,
+    end: usize,
+) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::new();
+    let mut offsets = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let (c, next) = char_at(input, pos);
+        offsets.push((text.len(), pos));
+        text.push(c);
+        pos = next;
+    }
+    offsets.push((text.len(), end));
+    (text, offsets)
+}
+
+// Returns the UTF-16 offset of the UTF-8 character offset `index`.
+pub(crate) fn utf16_offset(offsets: &[(usize, usize)], index: usize) -> usize {
+    let i = offsets
+        .binary_search_by_key(&index, |offset| offset.0)
+        .unwrap();
+    offsets[i].1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::dictionary_builder::*;
+    use crate::multi_segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn multi_segment_test() {
+        let segmenter = MultiSegmenter::new()
+            .with_segmenter(Script::Khmer, Segmenter::new(Dictionary::new(KHMER_DATA)))
+            .with_segmenter(Script::Lao, Segmenter::new(Dictionary::new(LAO_DATA)))
+            .with_segmenter(Script::Cj, Segmenter::new(Dictionary::new(CJ_DATA)));
+        let utf16: Vec<u16> = "ភាសាខ្មែរ abc123 ພາສາລາວ焼肉定食を食べる。ราคา 10 บาท"
+            .encode_utf16()
+            .collect();
+        // The Thai words are split into clusters without a Thai segmenter.
+        assert_eq!(
+            segmenter.segment(&utf16),
+            [9, 10, 16, 17, 21, 24, 26, 28, 29, 32, 33, 34, 35, 36, 37, 38, 40, 41, 42, 43, 44]
+        );
+
+        let data = build_words(DictionaryBuilder::bytes(0x0e00), &["ราคา", "บาท"]);
+        let thai = Segmenter::new(Dictionary::new(&data)).with_strategy(Strategy::Thai);
+        let segmenter = segmenter.with_segmenter(Script::Thai, thai);
+        assert_eq!(segmenter.segment(&utf16)[10..], [33, 37, 38, 40, 41, 44]);
+
+        // Text outside dictionary scripts follows UAX #29.
+        let utf16: Vec<u16> = "Pi is 3.14, don't round.ភាសាខ្មែរ".encode_utf16().collect();
+        assert_eq!(
+            segmenter.segment(&utf16),
+            [2, 3, 5, 6, 10, 11, 12, 17, 18, 23, 24, 33]
+        );
+
+        // The prolonged sound mark is Common but stays with the katakana.
+        let utf16: Vec<u16> = "コーヒーを飲む".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16)[0], 4);
+
+        // Ignorable characters stay in the run.
+        let utf16: Vec<u16> = "ភា\u{200b}សាខ្មែរ".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16), [2, 3, 5, 10]);
+        let khmer = Dictionary::new(KHMER_DATA).with_ignorable(&['\u{200b}', '\u{00ad}']);
+        let segmenter = segmenter.with_segmenter(Script::Khmer, Segmenter::new(khmer));
+        assert_eq!(segmenter.segment(&utf16), [10]);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::multi_segmenter::*;
+use core::ops::Range;
+
+/// What a segment holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SegmentKind {
+    /// A segment with a letter or a digit, such as a dictionary word.
+    Word,
+    Whitespace,
+    /// Anything else, such as punctuation, symbols or emoji.
+    Punctuation,
+}
+
+impl SegmentKind {
+    fn of(segment: &[u16]) -> Self {
+        let mut chars =
+            core::char::decode_utf16(segment.iter().cloned()).map(|c| c.unwrap_or('\u{fffd}'));
+        if chars.clone().any(char::is_alphanumeric) {
+            SegmentKind::Word
+        } else if chars.all(char::is_whitespace) {
+            SegmentKind::Whitespace
+        } else {
+            SegmentKind::Punctuation
+        }
+    }
+}
+
+impl<'a> MultiSegmenter<'a> {
+    /// Returns the end offset and the kind of each segment of `input`.
+    pub fn segment_kinds(&self, input: &[u16]) -> Vec<(usize, SegmentKind)> {
+        self.range_kinds(input, 0, input.len())
+    }
+
+    /// Returns the segment at `offset`, the one a double-click there
+    /// selects. At the end of the text, this is the last segment.
+    ///
+    /// Only the text between the safe positions around `offset` is
+    /// segmented, as in the other navigation methods.
+    pub fn word_at(&self, input: &[u16], offset: usize) -> Range<usize> {
+        if input.is_empty() {
+            return 0..0;
+        }
+        let offset = offset.min(input.len() - 1);
+        let start = self.safe_position_before(input, offset);
+        let end = self.safe_position_after(input, offset + 1);
+        let mut segment_start = start;
+        for boundary in self.segment(&input[start..end]) {
+            if offset < start + boundary {
+                return segment_start..start + boundary;
+            }
+            segment_start = start + boundary;
+        }
+        segment_start..end
+    }
+
+    /// Returns the start of the first word after `offset`, skipping
+    /// whitespace and punctuation, or the end of the text.
+    pub fn next_word_start(&self, input: &[u16], offset: usize) -> usize {
+        let mut start = self.safe_position_before(input, offset.min(input.len()));
+        while start < input.len() {
+            let end = self.safe_position_after(input, start + 1);
+            let mut segment_start = start;
+            for (segment_end, kind) in self.range_kinds(input, start, end) {
+                if segment_start > offset && kind == SegmentKind::Word {
+                    return segment_start;
+                }
+                segment_start = segment_end;
+            }
+            start = end;
+        }
+        input.len()
+    }
+
+    /// Returns the end of the first word ending after `offset`, skipping
+    /// whitespace and punctuation, or the end of the text.
+    pub fn next_word_end(&self, input: &[u16], offset: usize) -> usize {
+        let mut start = self.safe_position_before(input, offset.min(input.len()));
+        while start < input.len() {
+            let end = self.safe_position_after(input, start + 1);
+            let word_end = self
+                .range_kinds(input, start, end)
+                .into_iter()
+                .find(|(segment_end, kind)| *segment_end > offset && *kind == SegmentKind::Word);
+            if let Some((word_end, _)) = word_end {
+                return word_end;
+            }
+            start = end;
+        }
+        input.len()
+    }
+
+    /// Returns the start of the last word starting before `offset`,
+    /// skipping whitespace and punctuation, or 0.
+    pub fn previous_word_start(&self, input: &[u16], offset: usize) -> usize {
+        let mut end = self.safe_position_after(input, offset.min(input.len()));
+        while end > 0 {
+            let start = self.safe_position_before(input, end - 1);
+            let mut segment_start = start;
+            let mut word_start = None;
+            for (segment_end, kind) in self.range_kinds(input, start, end) {
+                if segment_start >= offset {
+                    break;
+                }
+                if kind == SegmentKind::Word {
+                    word_start = Some(segment_start);
+                }
+                segment_start = segment_end;
+            }
+            if let Some(word_start) = word_start {
+                return word_start;
+            }
+            end = start;
+        }
+        0
+    }
+
+    // Returns the end offset and the kind of each segment of
+    // input[start..end], between safe positions.
+    fn range_kinds(&self, input: &[u16], start: usize, end: usize) -> Vec<(usize, SegmentKind)> {
+        let mut segment_start = start;
+        self.segment(&input[start..end])
+            .into_iter()
+            .map(|boundary| {
+                let segment_end = start + boundary;
+                let kind = SegmentKind::of(&input[segment_start..segment_end]);
+                segment_start = segment_end;
+                (segment_end, kind)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::navigation::*;
+    use crate::script::*;
+    use crate::segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn navigation_test() {
+        let segmenter = MultiSegmenter::new()
+            .with_segmenter(Script::Khmer, Segmenter::new(Dictionary::new(KHMER_DATA)))
+            .with_segmenter(Script::Lao, Segmenter::new(Dictionary::new(LAO_DATA)))
+            .with_segmenter(Script::Cj, Segmenter::new(Dictionary::new(CJ_DATA)));
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ, ພາສາລາວພາສາລາວ 焼肉定食を食べる。"
+            .encode_utf16()
+            .collect();
+        let kinds = segmenter.segment_kinds(&utf16);
+        assert_eq!(
+            kinds.iter().map(|k| k.0).collect::<Vec<usize>>(),
+            [9, 18, 19, 20, 24, 27, 31, 34, 35, 37, 39, 40, 43, 44]
+        );
+        assert_eq!(kinds[2].1, SegmentKind::Punctuation);
+        assert_eq!(kinds[3].1, SegmentKind::Whitespace);
+        assert!(kinds[4..8].iter().all(|k| k.1 == SegmentKind::Word));
+
+        assert_eq!(segmenter.word_at(&utf16, 0), 0..9);
+        assert_eq!(segmenter.word_at(&utf16, 9), 9..18);
+        assert_eq!(segmenter.word_at(&utf16, 18), 18..19);
+        assert_eq!(segmenter.word_at(&utf16, 36), 35..37);
+        assert_eq!(segmenter.word_at(&utf16, 44), 43..44);
+        assert_eq!(segmenter.word_at(&[], 0), 0..0);
+
+        let next_starts: Vec<usize> = [0, 9, 24, 34, 43]
+            .iter()
+            .map(|offset| segmenter.next_word_start(&utf16, *offset))
+            .collect();
+        assert_eq!(next_starts, [9, 20, 27, 35, 44]);
+        let next_ends: Vec<usize> = [0, 9, 18, 35, 43]
+            .iter()
+            .map(|offset| segmenter.next_word_end(&utf16, *offset))
+            .collect();
+        assert_eq!(next_ends, [9, 18, 24, 37, 44]);
+        let previous_starts: Vec<usize> = [0, 19, 20, 24, 44]
+            .iter()
+            .map(|offset| segmenter.previous_word_start(&utf16, *offset))
+            .collect();
+        assert_eq!(previous_starts, [0, 9, 9, 20, 40]);
+
+        let safe: Vec<usize> = (0..=utf16.len())
+            .filter(|pos| segmenter.is_safe(&utf16, *pos))
+            .collect();
+        assert_eq!(safe, [0, 18, 20, 34, 35, 43, 44]);
+
+        // Segmenting only around the offset gives what the whole text does.
+        let utf16: Vec<u16> = "Don't ភាសាខ្មែរ 3.14,\r\npay ພາສາລາວ\n\n焼肉を食べる。 ok"
+            .encode_utf16()
+            .collect();
+        let kinds = segmenter.segment_kinds(&utf16);
+        let starts: Vec<(usize, usize, SegmentKind)> = kinds
+            .iter()
+            .scan(0, |start, (end, kind)| {
+                let segment = (*start, *end, *kind);
+                *start = *end;
+                Some(segment)
+            })
+            .collect();
+        let words = || starts.iter().filter(|s| s.2 == SegmentKind::Word);
+        for offset in 0..=utf16.len() + 1 {
+            let (start, end, _) = *starts
+                .iter()
+                .find(|s| offset < s.1)
+                .unwrap_or(starts.last().unwrap());
+            assert_eq!(segmenter.word_at(&utf16, offset), start..end);
+            let next_start = words().find(|s| s.0 > offset).map_or(utf16.len(), |s| s.0);
+            assert_eq!(segmenter.next_word_start(&utf16, offset), next_start);
+            let next_end = words().find(|s| s.1 > offset).map_or(utf16.len(), |s| s.1);
+            assert_eq!(segmenter.next_word_end(&utf16, offset), next_end);
+            let previous_start = words().rfind(|s| s.0 < offset).map_or(0, |s| s.0);
+            assert_eq!(
+                segmenter.previous_word_start(&utf16, offset),
+                previous_start
+            );
+        }
+    }
+}
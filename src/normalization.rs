@@ -0,0 +1,84 @@
+use crate::grapheme::*;
+use unicode_normalization::UnicodeNormalization;
+
+/// The Unicode normalization form applied to the text before matching.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    /// Canonical composition. Joins decomposed characters and puts
+    /// combining marks in canonical order.
+    Nfc,
+    /// Compatibility composition, like `Nfc` but also folding half-width
+    /// katakana and other compatibility characters.
+    Nfkc,
+}
+
+// Normalizes `input` one grapheme cluster at a time. Returns the text and,
+// for the end of each cluster, its offset in the text and in `input`.
+// Clusters with unpaired surrogates are copied as they are.
+pub(crate) fn normalize(input: &[u16], form: Normalization) -> (Vec<u16>, Vec<(usize, usize)>) {
+    let mut output = Vec::with_capacity(input.len());
+    let mut offsets = Vec::new();
+    let mut pos = 0;
+    while pos < input.len() {
+        let end = next_cluster(input, pos, input.len());
+        match String::from_utf16(&input[pos..end]) {
+            Ok(cluster) => match form {
+                Normalization::Nfc => {
+                    output.extend(cluster.nfc().collect::<String>().encode_utf16())
+                }
+                Normalization::Nfkc => {
+                    output.extend(cluster.nfkc().collect::<String>().encode_utf16())
+                }
+            },
+            Err(_) => output.extend_from_slice(&input[pos..end]),
+        }
+        offsets.push((output.len(), end));
+        pos = end;
+    }
+    (output, offsets)
+}
+
+// Maps boundaries in the normalized text back to `input`. Boundaries inside
+// a cluster that normalized to several characters are dropped.
+pub(crate) fn original_boundaries(boundaries: &[usize], offsets: &[(usize, usize)]) -> Vec<usize> {
+    boundaries
+        .iter()
+        .filter_map(|boundary| original_offset(*boundary, offsets))
+        .collect()
+}
+
+// Maps an offset in the normalized text back to `input`, if it is the start
+// of the text or the end of a cluster.
+pub(crate) fn original_offset(offset: usize, offsets: &[(usize, usize)]) -> Option<usize> {
+    if offset == 0 {
+        return Some(0);
+    }
+    offsets
+        .binary_search_by_key(&offset, |offset| offset.0)
+        .ok()
+        .map(|i| offsets[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalization::*;
+
+    #[test]
+    fn normalize_test() {
+        // e + COMBINING ACUTE ACCENT, and half-width ﾊﾟ.
+        let utf16: Vec<u16> = "e\u{301}ﾊﾟﾝ".encode_utf16().collect();
+        let (text, offsets) = normalize(&utf16, Normalization::Nfc);
+        assert_eq!(String::from_utf16(&text).unwrap(), "éﾊﾟﾝ");
+        assert_eq!(offsets, [(1, 2), (3, 4), (4, 5)]);
+
+        let (text, offsets) = normalize(&utf16, Normalization::Nfkc);
+        assert_eq!(String::from_utf16(&text).unwrap(), "éパン");
+        assert_eq!(offsets, [(1, 2), (2, 4), (3, 5)]);
+
+        // ㍿ expands to four characters; only its end maps back.
+        let utf16: Vec<u16> = "㍿".encode_utf16().collect();
+        let (text, offsets) = normalize(&utf16, Normalization::Nfkc);
+        assert_eq!(text.len(), 4);
+        assert_eq!(original_boundaries(&[2, 4], &offsets), [1]);
+    }
+}
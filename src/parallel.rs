@@ -0,0 +1,90 @@
+use crate::segmenter::*;
+use std::thread;
+
+impl<'a> Segmenter<'a> {
+    /// Returns the same boundaries as `segment`, splitting `input` at safe
+    /// positions into up to `threads` pieces segmented on their own threads.
+    /// Text without safe positions, such as CJ text without line breaks,
+    /// stays in one piece.
+    pub fn segment_parallel(&self, input: &[u16], threads: usize) -> Vec<usize> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for i in 1..threads {
+            let target = input.len() * i / threads;
+            if target <= start {
+                continue;
+            }
+            let end = self.safe_position_after(input, target);
+            if end >= input.len() {
+                break;
+            }
+            pieces.push(start..end);
+            start = end;
+        }
+        pieces.push(start..input.len());
+        if pieces.len() == 1 {
+            return self.segment(input);
+        }
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = pieces
+                .into_iter()
+                .map(|piece| {
+                    scope.spawn(move || {
+                        let mut boundaries = Vec::new();
+                        self.segment_into(&input[piece.clone()], &mut boundaries);
+                        for boundary in boundaries.iter_mut() {
+                            *boundary += piece.start;
+                        }
+                        boundaries
+                    })
+                })
+                .collect();
+            let mut boundaries = Vec::new();
+            for handle in handles {
+                boundaries.extend(handle.join().unwrap());
+            }
+            boundaries
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::parallel::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn parallel_test() {
+        assert_send_sync::<Dictionary>();
+        assert_send_sync::<Segmenter>();
+
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA)).with_strategy(Strategy::Khmer);
+        let utf16: Vec<u16> = "ខ្ញុំស្រឡាញ់អ្នក សួស្តីពិភពលោក។ ខ្ញុំចង់ទៅផ្សារ\n"
+            .repeat(50)
+            .encode_utf16()
+            .collect();
+        let expected = segmenter.segment(&utf16);
+        for threads in [0, 1, 2, 3, 8, 1000] {
+            assert_eq!(segmenter.segment_parallel(&utf16, threads), expected);
+        }
+        assert_eq!(segmenter.segment_parallel(&[], 4), []);
+
+        // CJ text splits at line breaks only.
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let utf16: Vec<u16> = "焼肉定食を食べる。\n".repeat(20).encode_utf16().collect();
+        assert_eq!(
+            segmenter.segment_parallel(&utf16, 4),
+            segmenter.segment(&utf16)
+        );
+        let utf16: Vec<u16> = "焼肉定食を食べる。".repeat(20).encode_utf16().collect();
+        assert_eq!(
+            segmenter.segment_parallel(&utf16, 4),
+            segmenter.segment(&utf16)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use unicode_script::{Script as UScript, UnicodeScript};
+
+/// Scripts written without spaces, segmented with a dictionary.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Script {
+    Khmer,
+    Lao,
+    Thai,
+    Myanmar,
+    /// Han, Hiragana and Katakana, as covered by `cjdict.dict`.
+    Cj,
+}
+
+// Returns the dictionary script of `c`, if any.
+pub(crate) fn dictionary_script(c: char) -> Option<Script> {
+    match c.script() {
+        UScript::Khmer => Some(Script::Khmer),
+        UScript::Lao => Some(Script::Lao),
+        UScript::Thai => Some(Script::Thai),
+        UScript::Myanmar => Some(Script::Myanmar),
+        UScript::Han | UScript::Hiragana | UScript::Katakana => Some(Script::Cj),
+        UScript::Common if is_kana_common(c) => Some(Script::Cj),
+        _ => None,
+    }
+}
+
+// Returns whether `c` is a Common character used with kana only, such as
+// the prolonged sound mark.
+fn is_kana_common(c: char) -> bool {
+    let extension = c.script_extension();
+    !extension.is_common()
+        && extension
+            .iter()
+            .all(|script| script == UScript::Hiragana || script == UScript::Katakana)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::*;
+
+    #[test]
+    fn dictionary_script_test() {
+        assert_eq!(dictionary_script('ក'), Some(Script::Khmer));
+        assert_eq!(dictionary_script('ກ'), Some(Script::Lao));
+        assert_eq!(dictionary_script('ก'), Some(Script::Thai));
+        assert_eq!(dictionary_script('က'), Some(Script::Myanmar));
+        assert_eq!(dictionary_script('漢'), Some(Script::Cj));
+        assert_eq!(dictionary_script('か'), Some(Script::Cj));
+        // The prolonged sound mark is Common, but used with kana only.
+        assert_eq!(dictionary_script('ー'), Some(Script::Cj));
+        assert_eq!(dictionary_script('。'), None);
+        assert_eq!(dictionary_script('a'), None);
+    }
+}
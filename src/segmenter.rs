@@ -0,0 +1,840 @@
+use crate::dictionary::*;
+use crate::engine;
+use crate::error::*;
+use crate::grapheme::*;
+use crate::lattice::*;
+use crate::multi_segmenter::*;
+use crate::normalization::*;
+use crate::trie::*;
+use crate::word_boundaries::*;
+
+/// How dictionary words are chosen.
+#[derive(Clone, Copy)]
+pub enum Strategy<'a> {
+    /// Greedy longest match from the start of the text.
+    Forward,
+    /// Greedy longest match from the end of the text. The dictionary has to
+    /// hold the reversed words, see `DictionaryBuilder::reversed`.
+    Backward(Dictionary<'a>),
+    /// Runs forward and backward matching and picks the result with fewer
+    /// words, then with fewer single-character words. Ties go to backward
+    /// matching.
+    Bidirectional(Dictionary<'a>),
+    /// Finds the segmentation with the fewest segments by dynamic
+    /// programming over all dictionary matches, then the one with the
+    /// fewest unknown characters. Remaining ties are broken by `TieBreak`.
+    MinWords(TieBreak),
+    /// The rules of ICU's Khmer break engine: up to three words of
+    /// lookahead, unknown text combined with short neighbouring words, and
+    /// no boundary before a combining mark. Meant for `khmerdict.dict`.
+    Khmer,
+    /// The rules of ICU's Lao break engine, like `Khmer`. Pre-base vowels
+    /// start words. Unlike in ICU, the ellipsis U+0EAF and the repetition
+    /// mark U+0EC6 also attach to the preceding word, as in `Thai`. Meant
+    /// for `laodict.dict`.
+    Lao,
+    /// The rules of ICU's Thai break engine, like `Lao` with PAIYANNOI
+    /// U+0E2F and MAIYAMOK U+0E46 as suffixes. Meant for ICU's
+    /// `thaidict.dict`.
+    Thai,
+    /// The rules of ICU's Burmese break engine, like `Khmer`. Medials and
+    /// other combining marks stay with the preceding consonant. Meant for
+    /// ICU's `burmesedict.dict`.
+    Burmese,
+}
+
+/// Which of several equally good segmentations `Strategy::MinWords` picks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TieBreak {
+    /// Prefer longer words at the start of the text, like forward matching.
+    LongerFirst,
+    /// Prefer longer words at the end of the text, like backward matching.
+    LongerLast,
+}
+
+/// Dictionary based word segmenter.
+///
+/// Unlike `DictionaryIterator`, text that doesn't match the dictionary is
+/// skipped one grapheme cluster at a time, so the whole input is always
+/// segmented. Boundaries never fall inside a grapheme cluster.
+#[derive(Clone)]
+pub struct Segmenter<'a> {
+    dictionary: Dictionary<'a>,
+    strategy: Strategy<'a>,
+    normalization: Option<Normalization>,
+}
+
+impl<'a> Segmenter<'a> {
+    pub fn new(dictionary: Dictionary<'a>) -> Self {
+        Self {
+            dictionary,
+            strategy: Strategy::Forward,
+            normalization: None,
+        }
+    }
+
+    /// Returns a segmenter for the language of `locale`, such as `km` or
+    /// `ja-JP`, using the dictionaries enabled by the `khmer`, `lao` and
+    /// `cj` features. Returns `None` if none of them covers the language.
+    pub fn for_locale(locale: &str) -> Option<Segmenter<'static>> {
+        let language = locale
+            .split(['-', '_'])
+            .next()
+            .unwrap()
+            .to_ascii_lowercase();
+        let bundled: Option<(&'static [u8], _)> = match language.as_str() {
+            #[cfg(feature = "khmer")]
+            "km" => Some((include_bytes!("../data/khmerdict.dict"), Strategy::Khmer)),
+            #[cfg(feature = "lao")]
+            "lo" => Some((include_bytes!("../data/laodict.dict"), Strategy::Lao)),
+            #[cfg(feature = "cj")]
+            "ja" | "zh" => Some((include_bytes!("../data/cjdict.dict"), Strategy::Forward)),
+            _ => None,
+        };
+        let (data, strategy) = bundled?;
+        Some(Segmenter::new(Dictionary::new(data)).with_strategy(strategy))
+    }
+
+    /// Sets the strategy used by `segment` and `word_boundaries`.
+    pub fn with_strategy(mut self, strategy: Strategy<'a>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Normalizes the text before matching it against the dictionary, in
+    /// every method. Boundaries and lattice offsets are still offsets into
+    /// the original text, and never fall inside a grapheme cluster of it.
+    pub fn with_normalization(mut self, normalization: Option<Normalization>) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn dictionary(&self) -> &Dictionary<'a> {
+        &self.dictionary
+    }
+
+    /// Returns the end offset of each segment of `input`.
+    pub fn segment(&self, input: &[u16]) -> Vec<usize> {
+        self.segment_with(input, self.strategy)
+    }
+
+    /// Returns the end offset of each segment of `input`, using `strategy`
+    /// instead of the segmenter's own.
+    pub fn segment_with(&self, input: &[u16], strategy: Strategy<'a>) -> Vec<usize> {
+        let mut boundaries = Vec::new();
+        self.segment_ranges(input, strategy, &mut boundaries, &mut Scratch::default());
+        boundaries
+    }
+
+    /// Appends the end offset of each segment of `input` to `boundaries`.
+    /// The working buffers of the strategy are allocated once per call and
+    /// reused for every word, and `segment_batch` reuses them across its
+    /// inputs. Normalizing the text allocates for each safe range.
+    pub fn segment_into(&self, input: &[u16], boundaries: &mut Vec<usize>) {
+        self.segment_into_with(input, boundaries, &mut Scratch::default());
+    }
+
+    // Like `segment_into`, with the working buffers of a caller that
+    // segments several inputs.
+    pub(crate) fn segment_into_with(
+        &self,
+        input: &[u16],
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        self.segment_ranges(input, self.strategy, boundaries, scratch);
+    }
+
+    // Appends the boundaries of `input`, segmenting it between safe
+    // positions.
+    fn segment_ranges(
+        &self,
+        input: &[u16],
+        strategy: Strategy<'a>,
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        let mut start = 0;
+        while start < input.len() {
+            let end = self.safe_position_after(input, start + 1);
+            self.segment_range_with(strategy, input, start, end, boundaries, scratch);
+            start = end;
+        }
+    }
+
+    /// Returns every dictionary match in `input`, plus unknown cluster
+    /// edges, regardless of the strategy.
+    pub fn lattice(&self, input: &[u16]) -> Lattice {
+        match self.normalization {
+            Some(form) => {
+                let (text, offsets) = normalize(input, form);
+                Lattice::new(&self.dictionary, &text, 0, text.len()).to_original(&offsets)
+            }
+            None => Lattice::new(&self.dictionary, input, 0, input.len()),
+        }
+    }
+
+    /// Returns up to `n` segmentations of `input` with their scores, best
+    /// first. See `Lattice::n_best`.
+    pub fn n_best(
+        &self,
+        input: &[u16],
+        n: usize,
+        ranking: Ranking,
+    ) -> Result<Vec<(Vec<usize>, i64)>, Error> {
+        self.lattice(input).n_best(n, ranking)
+    }
+
+    /// Returns a random access view over the word boundaries of `input`.
+    pub fn word_boundaries<'s>(&'s self, input: &'s [u16]) -> WordBoundaries<'s, 'a> {
+        WordBoundaries::new(self, input)
+    }
+
+    // Appends the boundaries in start+1..=end to `boundaries`.
+    // `start` and `end` have to be safe positions.
+    pub(crate) fn segment_range(
+        &self,
+        input: &[u16],
+        start: usize,
+        end: usize,
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        self.segment_range_with(self.strategy, input, start, end, boundaries, scratch);
+    }
+
+    fn segment_range_with(
+        &self,
+        strategy: Strategy<'a>,
+        input: &[u16],
+        start: usize,
+        end: usize,
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        match self.normalization {
+            Some(form) => {
+                let (text, offsets) = normalize(&input[start..end], form);
+                let mut normalized = Vec::new();
+                self.segment_text_with(strategy, &text, 0, text.len(), &mut normalized, scratch);
+                let original = original_boundaries(&normalized, &offsets);
+                boundaries.extend(original.into_iter().map(|boundary| start + boundary));
+            }
+            None => self.segment_text_with(strategy, input, start, end, boundaries, scratch),
+        }
+    }
+
+    // Like `segment_range_with`, on text that is already normalized.
+    fn segment_text_with(
+        &self,
+        strategy: Strategy<'a>,
+        input: &[u16],
+        start: usize,
+        end: usize,
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        let lengths = &mut scratch.lengths;
+        // Reversed words skip the same characters as the segmenter's.
+        let reversed =
+            |reversed: Dictionary<'a>| reversed.with_ignorable(self.dictionary.ignorable());
+        match strategy {
+            Strategy::Forward => self.forward(input, start, end, boundaries, lengths),
+            Strategy::Backward(dictionary) => backward(
+                &reversed(dictionary),
+                input,
+                start,
+                end,
+                boundaries,
+                lengths,
+            ),
+            Strategy::Bidirectional(dictionary) => {
+                let forward = &mut scratch.forward;
+                forward.clear();
+                self.forward(input, start, end, forward, lengths);
+                let backward_boundaries = &mut scratch.backward;
+                backward_boundaries.clear();
+                let reversed = reversed(dictionary);
+                backward(&reversed, input, start, end, backward_boundaries, lengths);
+                let single_chars = |segments: &[usize]| {
+                    let mut count = 0;
+                    let mut pos = start;
+                    for boundary in segments.iter() {
+                        if next_cluster(input, pos, end) == *boundary {
+                            count += 1;
+                        }
+                        pos = *boundary;
+                    }
+                    count
+                };
+                let forward_is_better = (forward.len(), single_chars(forward))
+                    < (backward_boundaries.len(), single_chars(backward_boundaries));
+                if forward_is_better {
+                    boundaries.extend_from_slice(forward);
+                } else {
+                    boundaries.extend_from_slice(backward_boundaries);
+                }
+            }
+            Strategy::MinWords(tie_break) => {
+                self.min_words(tie_break, input, start, end, boundaries, scratch)
+            }
+            Strategy::Khmer => engine::segment(
+                &self.dictionary,
+                &engine::KHMER,
+                input,
+                start,
+                end,
+                boundaries,
+            ),
+            Strategy::Lao => engine::segment(
+                &self.dictionary,
+                &engine::LAO,
+                input,
+                start,
+                end,
+                boundaries,
+            ),
+            Strategy::Thai => engine::segment(
+                &self.dictionary,
+                &engine::THAI,
+                input,
+                start,
+                end,
+                boundaries,
+            ),
+            Strategy::Burmese => engine::segment(
+                &self.dictionary,
+                &engine::BURMESE,
+                input,
+                start,
+                end,
+                boundaries,
+            ),
+        }
+    }
+
+    fn forward(
+        &self,
+        input: &[u16],
+        start: usize,
+        end: usize,
+        boundaries: &mut Vec<usize>,
+        lengths: &mut Vec<(usize, Option<i32>)>,
+    ) {
+        let mut pos = start;
+        while pos < end {
+            matches_into(&self.dictionary, input[pos..end].iter(), lengths);
+            let longest = lengths
+                .iter()
+                .rev()
+                .find(|(length, _)| is_grapheme_boundary(input, pos + length));
+            pos = match longest {
+                Some((length, _)) => pos + length,
+                None => next_cluster(input, pos, end),
+            };
+            boundaries.push(pos);
+        }
+    }
+
+    fn min_words(
+        &self,
+        tie_break: TieBreak,
+        input: &[u16],
+        start: usize,
+        end: usize,
+        boundaries: &mut Vec<usize>,
+        scratch: &mut Scratch,
+    ) {
+        let Scratch {
+            lengths,
+            best,
+            edges,
+            ..
+        } = scratch;
+        let length = end - start;
+        best.clear();
+        best.resize(length + 1, None);
+        match tie_break {
+            // Paths from each position to the end; walk forward.
+            TieBreak::LongerFirst => {
+                best[length] = Some(((0, 0), 0));
+                for i in (0..length).rev() {
+                    edges_into(&self.dictionary, input, start + i, end, lengths, edges);
+                    for edge in edges.iter() {
+                        relax(best, edge.end - start, i, edge);
+                    }
+                }
+                let mut i = 0;
+                while i < length {
+                    i += best[i].unwrap().1;
+                    boundaries.push(start + i);
+                }
+            }
+            // Paths from the start to each position; walk backward.
+            TieBreak::LongerLast => {
+                best[0] = Some(((0, 0), 0));
+                for i in 0..length {
+                    edges_into(&self.dictionary, input, start + i, end, lengths, edges);
+                    for edge in edges.iter() {
+                        relax(best, i, edge.end - start, edge);
+                    }
+                }
+                let first = boundaries.len();
+                let mut i = length;
+                while i > 0 {
+                    boundaries.push(start + i);
+                    i -= best[i].unwrap().1;
+                }
+                boundaries[first..].reverse();
+            }
+        }
+    }
+
+    // Returns whether no dictionary word or grapheme cluster can span across
+    // `pos`, so that segmentation can restart there.
+    pub(crate) fn is_safe(&self, input: &[u16], pos: usize) -> bool {
+        if pos == 0 || pos >= input.len() {
+            return true;
+        }
+        if !is_grapheme_boundary(input, pos) {
+            return false;
+        }
+        // No word spans a line break either, which matters for dictionaries
+        // such as cjdict.dict that don't restrict the characters of words.
+        if is_line_break(input[pos - 1]) {
+            return true;
+        }
+        // Clusters are normalized on their own, so only the units next to
+        // `pos` after normalizing its neighbouring clusters matter.
+        let (before, after) = match self.normalization {
+            Some(form) => {
+                let previous = previous_cluster(input, pos, 0);
+                let next = next_cluster(input, pos, input.len());
+                let before = normalize(&input[previous..pos], form).0;
+                let after = normalize(&input[pos..next], form).0;
+                (*before.last().unwrap(), after[0])
+            }
+            None => (input[pos - 1], input[pos]),
+        };
+        !self.dictionary.contains_unit(before) || !self.dictionary.contains_unit(after)
+    }
+
+    // Returns the last safe position at or before `pos`.
+    pub(crate) fn safe_position_before(&self, input: &[u16], pos: usize) -> usize {
+        (0..=pos).rev().find(|p| self.is_safe(input, *p)).unwrap()
+    }
+
+    // Returns the first safe position at or after `pos`.
+    pub(crate) fn safe_position_after(&self, input: &[u16], pos: usize) -> usize {
+        (pos..input.len())
+            .find(|p| self.is_safe(input, *p))
+            .unwrap_or(input.len())
+    }
+}
+
+// The (segments, unknown units) score of the best path to a position, and
+// the length of its segment at that position.
+type Best = ((usize, usize), usize);
+
+// Working buffers of the strategies, kept between safe ranges and inputs so
+// that segmenting doesn't allocate per word.
+#[derive(Default)]
+pub(crate) struct Scratch {
+    // The dictionary matches at one position.
+    lengths: Vec<(usize, Option<i32>)>,
+    // The boundaries of both directions for `Strategy::Bidirectional`.
+    forward: Vec<usize>,
+    backward: Vec<usize>,
+    // The best paths and the edges at one position for
+    // `Strategy::MinWords`.
+    best: Vec<Option<Best>>,
+    edges: Vec<Edge>,
+}
+
+// Updates the best path at `to` with `edge` appended to the path at `from`.
+// Offsets are relative to the start of `best`.
+fn relax(best: &mut [Option<Best>], from: usize, to: usize, edge: &Edge) {
+    if let Some(((segments, unknown), _)) = best[from] {
+        let length = from.max(to) - from.min(to);
+        let score = (
+            segments + 1,
+            unknown
+                + match edge.kind {
+                    EdgeKind::Unknown => length,
+                    EdgeKind::Word => 0,
+                },
+        );
+        let better = match best[to] {
+            Some((best_score, best_length)) => {
+                score < best_score || (score == best_score && length > best_length)
+            }
+            None => true,
+        };
+        if better {
+            best[to] = Some((score, length));
+        }
+    }
+}
+
+// Returns whether `c` ends a line: LF, CR, NEL, LINE SEPARATOR or
+// PARAGRAPH SEPARATOR.
+pub(crate) fn is_line_break(c: u16) -> bool {
+    matches!(c, 0x0a | 0x0d | 0x85 | 0x2028 | 0x2029)
+}
+
+fn backward(
+    reversed: &Dictionary,
+    input: &[u16],
+    start: usize,
+    end: usize,
+    boundaries: &mut Vec<usize>,
+    lengths: &mut Vec<(usize, Option<i32>)>,
+) {
+    let first = boundaries.len();
+    let mut pos = end;
+    while pos > start {
+        boundaries.push(pos);
+        // Ignorable characters after a word belong to it, as with forward
+        // matching, unless there is no word before them.
+        let mut word_end = pos;
+        while word_end > start && reversed.is_ignorable(previous_char_at(input, word_end)) {
+            word_end = previous_char(input, word_end, start);
+        }
+        if word_end == start {
+            word_end = pos;
+        }
+        matches_into(reversed, input[start..word_end].iter().rev(), lengths);
+        let longest = lengths
+            .iter()
+            .rev()
+            .find(|(length, _)| is_grapheme_boundary(input, word_end - length));
+        pos = match longest {
+            // Ignorable characters the match started with go to the word
+            // before it.
+            Some((length, _)) => reversed.skip_ignorable(input, word_end - length, word_end),
+            None => previous_cluster(input, word_end, start),
+        };
+    }
+    boundaries[first..].reverse();
+}
+
+// Replaces the contents of `lengths` with the lengths of the dictionary
+// words at the start of `units`, with their values if the dictionary has
+// them, shortest first.
+pub(crate) fn matches_into<'u>(
+    dictionary: &Dictionary,
+    units: impl Iterator<Item = &'u u16>,
+    lengths: &mut Vec<(usize, Option<i32>)>,
+) {
+    lengths.clear();
+    let data = dictionary.data();
+    let mut trie = dictionary.trie();
+    let has_values = dictionary.has_values();
+    let value = |trie: &dyn Trie| {
+        if has_values {
+            Some(trie.value(data))
+        } else {
+            None
+        }
+    };
+    let mut units = units.peekable();
+    let mut length = 0;
+    let mut done = false;
+    while let Some(c) = units.next() {
+        // Ignorable characters are skipped, and belong to the word before
+        // them.
+        let skip = match length {
+            0 => 0,
+            _ => dictionary.ignorable_length(*c, units.peek().map(|c| **c)),
+        };
+        if skip > 0 {
+            if skip == 2 {
+                units.next();
+            }
+            if let Some(last) = lengths.last_mut().filter(|last| last.0 == length) {
+                last.0 += skip;
+            }
+            length += skip;
+            continue;
+        }
+        if done {
+            break;
+        }
+        let result = match length {
+            0 => trie.first(data, dictionary.transform(*c)),
+            _ => trie.next(data, dictionary.transform(*c)),
+        };
+        length += 1;
+        match result {
+            TrieResult::FinalValue => {
+                lengths.push((length, value(&trie)));
+                done = true;
+            }
+            TrieResult::Intermediate => lengths.push((length, value(&trie))),
+            TrieResult::NoMatch => break,
+            TrieResult::NoValue => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary_builder::*;
+    use crate::segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn segment_test() {
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        assert_eq!(segmenter.segment(&utf16), [9, 18]);
+
+        let utf16: Vec<u16> = "ພາສາລາວພາສາລາວ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(LAO_DATA));
+        assert_eq!(segmenter.segment(&utf16), [4, 7, 11, 14]);
+
+        let utf16: Vec<u16> = "焼肉定食を食べる".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        assert_eq!(segmenter.segment(&utf16), [2, 4, 5, 8]);
+    }
+
+    #[test]
+    fn strategy_test() {
+        // 研究生命起源: forward matching takes 研究生 and leaves 命 alone.
+        let data = build_words(
+            DictionaryBuilder::uchars(),
+            &["研究", "研究生", "生命", "命", "起源"],
+        );
+        let dictionary = Dictionary::new(&data);
+        let reversed_data = DictionaryBuilder::reversed(&dictionary).build().unwrap();
+        let reversed = Dictionary::new(&reversed_data);
+
+        let utf16: Vec<u16> = "研究生命起源".encode_utf16().collect();
+        let segmenter = Segmenter::new(dictionary);
+        assert_eq!(segmenter.segment(&utf16), [3, 4, 6]);
+        assert_eq!(
+            segmenter.segment_with(&utf16, Strategy::Backward(reversed)),
+            [2, 4, 6]
+        );
+        assert_eq!(
+            segmenter.segment_with(&utf16, Strategy::Bidirectional(reversed)),
+            [2, 4, 6]
+        );
+
+        // Backward matching also skips unknown text.
+        let utf16: Vec<u16> = "x研究生命 研究生".encode_utf16().collect();
+        assert_eq!(
+            segmenter.segment_with(&utf16, Strategy::Backward(reversed)),
+            [1, 3, 5, 6, 9]
+        );
+        let segmenter = segmenter.with_strategy(Strategy::Bidirectional(reversed));
+        assert_eq!(segmenter.segment(&utf16), [1, 3, 5, 6, 9]);
+        let boundaries: Vec<usize> = segmenter.word_boundaries(&utf16).rev().collect();
+        assert_eq!(boundaries, [9, 6, 5, 3, 1]);
+    }
+
+    #[test]
+    fn min_words_test() {
+        let data = build_words(
+            DictionaryBuilder::uchars(),
+            &["a", "ab", "abc", "bcde", "cd", "d"],
+        );
+        let segmenter = Segmenter::new(Dictionary::new(&data));
+
+        // Greedy matching takes abc and leaves e unknown.
+        let utf16: Vec<u16> = "abcde".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16), [3, 4, 5]);
+        let strategy = Strategy::MinWords(TieBreak::LongerFirst);
+        assert_eq!(segmenter.segment_with(&utf16, strategy), [1, 5]);
+
+        // ab|cd and abc|d are both two words.
+        let utf16: Vec<u16> = "abcd".encode_utf16().collect();
+        assert_eq!(segmenter.segment_with(&utf16, strategy), [3, 4]);
+        let strategy = Strategy::MinWords(TieBreak::LongerLast);
+        assert_eq!(segmenter.segment_with(&utf16, strategy), [2, 4]);
+
+        // Unknown characters count as segments.
+        let utf16: Vec<u16> = "xabcdey".encode_utf16().collect();
+        assert_eq!(segmenter.segment_with(&utf16, strategy), [1, 2, 6, 7]);
+
+        let utf16: Vec<u16> = "ພາສາລາວພາສາລາວ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(LAO_DATA));
+        assert_eq!(segmenter.segment_with(&utf16, strategy), [4, 7, 11, 14]);
+    }
+
+    #[test]
+    fn scratch_test() {
+        let first: Vec<u16> = "ພາສາລາວພາສາລາວ".encode_utf16().collect();
+        let second: Vec<u16> = "ລາວພາສາລາວພາສາ".encode_utf16().collect();
+        for strategy in [Strategy::Forward, Strategy::MinWords(TieBreak::LongerFirst)] {
+            let segmenter = Segmenter::new(Dictionary::new(LAO_DATA)).with_strategy(strategy);
+            let mut scratch = Scratch::default();
+            let mut boundaries = Vec::new();
+            segmenter.segment_into_with(&first, &mut boundaries, &mut scratch);
+            assert_eq!(boundaries, segmenter.segment(&first));
+
+            // The second input of the same length reuses the buffers.
+            let lengths = (scratch.lengths.as_ptr(), scratch.lengths.capacity());
+            let best = (scratch.best.as_ptr(), scratch.best.capacity());
+            let edges = (scratch.edges.as_ptr(), scratch.edges.capacity());
+            boundaries.clear();
+            segmenter.segment_into_with(&second, &mut boundaries, &mut scratch);
+            assert_eq!(boundaries, segmenter.segment(&second));
+            assert_eq!(
+                (scratch.lengths.as_ptr(), scratch.lengths.capacity()),
+                lengths
+            );
+            assert_eq!((scratch.best.as_ptr(), scratch.best.capacity()), best);
+            assert_eq!((scratch.edges.as_ptr(), scratch.edges.capacity()), edges);
+        }
+    }
+
+    #[test]
+    fn unknown_test() {
+        // Latin letters aren't in the Khmer dictionary, so each one is a segment.
+        let utf16: Vec<u16> = "ab ភាសាខ្មែរ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        assert_eq!(segmenter.segment(&utf16), [1, 2, 3, 12]);
+        assert!(segmenter.is_safe(&utf16, 3));
+        assert!(!segmenter.is_safe(&utf16, 5));
+        assert_eq!(segmenter.safe_position_before(&utf16, 8), 3);
+        assert_eq!(segmenter.safe_position_after(&utf16, 4), 12);
+
+        // Surrogate pairs are never split.
+        let utf16: Vec<u16> = "𠮷野家".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        assert_eq!(segmenter.segment(&utf16)[0], 2);
+    }
+
+    #[test]
+    fn grapheme_test() {
+        // ក and ក្ស end inside the cluster ក្សា, so neither is taken.
+        let data = build_words(DictionaryBuilder::bytes(0x1780), &["ក", "ក្ស", "កា"]);
+        let dictionary = Dictionary::new(&data);
+        let reversed_data = DictionaryBuilder::reversed(&dictionary).build().unwrap();
+        let reversed = Dictionary::new(&reversed_data);
+        let segmenter = Segmenter::new(dictionary);
+        let utf16: Vec<u16> = "ក្សាកា".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16), [4, 6]);
+        for strategy in [
+            Strategy::Backward(reversed),
+            Strategy::Bidirectional(reversed),
+            Strategy::MinWords(TieBreak::LongerFirst),
+            Strategy::MinWords(TieBreak::LongerLast),
+        ]
+        .iter()
+        {
+            assert_eq!(segmenter.segment_with(&utf16, *strategy), [4, 6]);
+        }
+
+        // Unknown text is skipped a cluster at a time, and a combining mark
+        // outside the dictionary doesn't make a safe position.
+        let utf16: Vec<u16> = "👩\u{200d}👩e\u{301}ក\u{301}".encode_utf16().collect();
+        assert_eq!(segmenter.segment(&utf16), [5, 7, 9]);
+        assert!(!segmenter.is_safe(&utf16, 8));
+    }
+
+    #[test]
+    #[cfg(all(feature = "khmer", feature = "lao", feature = "cj"))]
+    fn for_locale_test() {
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ".encode_utf16().collect();
+        let segmenter = Segmenter::for_locale("km-KH").unwrap();
+        assert_eq!(segmenter.segment(&utf16), [9, 18]);
+
+        let utf16: Vec<u16> = "ພາສາລາວພາສາລາວ".encode_utf16().collect();
+        let segmenter = Segmenter::for_locale("lo").unwrap();
+        assert_eq!(segmenter.segment(&utf16), [4, 7, 11, 14]);
+
+        let utf16: Vec<u16> = "焼肉定食を食べる".encode_utf16().collect();
+        let segmenter = Segmenter::for_locale("ja_JP").unwrap();
+        assert_eq!(segmenter.segment(&utf16), [2, 4, 5, 8]);
+        assert!(Segmenter::for_locale("ZH-Hant").is_some());
+
+        assert!(Segmenter::for_locale("en").is_none());
+        assert!(Segmenter::for_locale("").is_none());
+    }
+
+    #[test]
+    fn normalization_test() {
+        // Half-width ｺｰﾋｰ and ﾊﾟﾝ; ﾊﾟ is a single cluster.
+        let utf16: Vec<u16> = "ｺｰﾋｰとﾊﾟﾝを食べる".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        assert_eq!(segmenter.segment(&utf16), [1, 2, 3, 4, 5, 7, 8, 9, 12]);
+        let segmenter = segmenter.with_normalization(Some(Normalization::Nfkc));
+        assert_eq!(segmenter.segment(&utf16), [4, 5, 8, 9, 12]);
+        // Every API sees the normalized text.
+        let boundaries: Vec<usize> = segmenter.word_boundaries(&utf16).collect();
+        assert_eq!(boundaries, segmenter.segment(&utf16));
+        assert!(segmenter.word_boundaries(&utf16).is_boundary(8));
+        assert!(!segmenter.word_boundaries(&utf16).is_boundary(6));
+        let lattice = segmenter.lattice(&utf16);
+        assert!(lattice.edges_from(0).iter().any(|edge| edge.end == 4));
+        assert!(lattice.edges_from(6).is_empty());
+        let n_best = segmenter.n_best(&utf16, 1, Ranking::Cost).unwrap();
+        assert_eq!(n_best[0].0, [4, 5, 8, 9, 12]);
+
+        // ㍱ and ﬁ expand to several characters that aren't words; each is
+        // one unknown segment.
+        for text in ["㍱", "ﬁ"].iter() {
+            let expanded: Vec<u16> = text.encode_utf16().collect();
+            assert_eq!(segmenter.segment(&expanded), [1]);
+            let lattice = segmenter.lattice(&expanded);
+            assert_eq!(
+                lattice.edges().collect::<Vec<_>>(),
+                [(
+                    0,
+                    &Edge {
+                        end: 1,
+                        value: None,
+                        kind: EdgeKind::Unknown
+                    }
+                )]
+            );
+            assert_eq!(
+                segmenter.n_best(&expanded, 2, Ranking::Cost),
+                Ok(vec![(vec![1], 255)])
+            );
+        }
+        let mixed: Vec<u16> = "焼肉㍱".encode_utf16().collect();
+        let n_best = segmenter.n_best(&mixed, 1, Ranking::Cost).unwrap();
+        assert_eq!(n_best[0].0, segmenter.segment(&mixed));
+        let segmenter = segmenter.with_normalization(Some(Normalization::Nfc));
+        assert_eq!(segmenter.segment(&utf16), [1, 2, 3, 4, 5, 7, 8, 9, 12]);
+    }
+
+    #[test]
+    fn ignorable_test() {
+        const IGNORABLE: &[char] = &['\u{200b}', '\u{00ad}'];
+        let utf16: Vec<u16> = "ភា\u{200b}សាខ្មែរ\u{00ad}ភាសាខ្មែរ".encode_utf16().collect();
+        let dictionary = Dictionary::new(KHMER_DATA);
+        for strategy in [
+            Strategy::Forward,
+            Strategy::Khmer,
+            Strategy::MinWords(TieBreak::LongerFirst),
+        ] {
+            let segmenter = Segmenter::new(dictionary).with_strategy(strategy);
+            assert_eq!(segmenter.segment(&utf16), [2, 3, 5, 10, 11, 20]);
+            let segmenter =
+                Segmenter::new(dictionary.with_ignorable(IGNORABLE)).with_strategy(strategy);
+            assert_eq!(segmenter.segment(&utf16), [11, 20]);
+        }
+
+        // Matching from the end skips them too, and keeps a trailing one
+        // with the word before it.
+        let data = DictionaryBuilder::reversed(&dictionary).build().unwrap();
+        let reversed = Dictionary::new(&data);
+        let trailing: Vec<u16> = "ភាសា\u{200b}ខ្មែរ\u{200b}".encode_utf16().collect();
+        for strategy in [
+            Strategy::Forward,
+            Strategy::Backward(reversed),
+            Strategy::Bidirectional(reversed),
+        ] {
+            let segmenter =
+                Segmenter::new(dictionary.with_ignorable(IGNORABLE)).with_strategy(strategy);
+            assert_eq!(segmenter.segment(&utf16), [11, 20]);
+            assert_eq!(segmenter.segment(&trailing), [11]);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::grapheme::*;
+use crate::multi_segmenter::*;
+use crate::script::*;
+
+impl<'a> MultiSegmenter<'a> {
+    /// Returns a copy of `input` with `separator`, such as U+200B ZERO WIDTH
+    /// SPACE, inserted between words of the scripts with a segmenter, and
+    /// the offset of each inserted separator in the copy.
+    ///
+    /// Nothing is inserted next to other text, so existing spaces and
+    /// separators are left alone and never doubled.
+    pub fn insert_separator(&self, input: &[u16], separator: &[u16]) -> (Vec<u16>, Vec<usize>) {
+        let segmented = |c: char| dictionary_script(c).is_some_and(|s| self.segmenter(s).is_some());
+        let mut output = Vec::with_capacity(input.len());
+        let mut inserted = Vec::new();
+        let mut pos = 0;
+        for boundary in self.segment(input) {
+            output.extend_from_slice(&input[pos..boundary]);
+            if boundary < input.len()
+                && segmented(char_at(input, boundary).0)
+                && segmented(char_at(input, previous_char(input, boundary, 0)).0)
+            {
+                inserted.push(output.len());
+                output.extend_from_slice(separator);
+            }
+            pos = boundary;
+        }
+        (output, inserted)
+    }
+}
+
+/// Removes the separators that `MultiSegmenter::insert_separator` inserted
+/// at the offsets `inserted` of `input`, keeping any that were already in
+/// the text. Returns the text and, for each of its offsets including the
+/// end, the corresponding offset in `input`.
+pub fn strip_separator(
+    input: &[u16],
+    separator: &[u16],
+    inserted: &[usize],
+) -> (Vec<u16>, Vec<usize>) {
+    let mut output = Vec::with_capacity(input.len());
+    let mut offsets = Vec::with_capacity(input.len() + 1);
+    let mut inserted = inserted.iter().peekable();
+    let mut pos = 0;
+    while pos < input.len() {
+        if inserted.next_if_eq(&&pos).is_some() {
+            pos += separator.len();
+            continue;
+        }
+        output.push(input[pos]);
+        offsets.push(pos);
+        pos += 1;
+    }
+    offsets.push(input.len());
+    (output, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::segmenter::*;
+    use crate::separator::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const LAO_DATA: &[u8; 162620] = include_bytes!("../data/laodict.dict");
+
+    #[test]
+    fn separator_test() {
+        let segmenter = MultiSegmenter::new()
+            .with_segmenter(Script::Khmer, Segmenter::new(Dictionary::new(KHMER_DATA)))
+            .with_segmenter(Script::Lao, Segmenter::new(Dictionary::new(LAO_DATA)));
+        let text = "ភាសាខ្មែរភាសាខ្មែរ ភាសាខ្មែរ\u{200b}ភាសាខ្មែរ abc ພາສາລາວພາສາລາວ";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let (separated, inserted) = segmenter.insert_separator(&utf16, &[0x200b]);
+        assert_eq!(
+            String::from_utf16(&separated).unwrap(),
+            "ភាសាខ្មែរ\u{200b}ភាសាខ្មែរ ភាសាខ្មែរ\u{200b}ភាសាខ្មែរ abc ພາສາ\u{200b}ລາວ\u{200b}ພາສາ\u{200b}ລາວ"
+        );
+        assert_eq!(inserted, [9, 48, 52, 57]);
+
+        // The separator already between the third and fourth Khmer words
+        // stays.
+        let (stripped, offsets) = strip_separator(&separated, &[0x200b], &inserted);
+        assert_eq!(stripped, utf16);
+        assert_eq!(offsets.len(), utf16.len() + 1);
+        assert_eq!(offsets[8..11], [8, 10, 11]);
+        for (i, offset) in offsets[..utf16.len()].iter().enumerate() {
+            assert_eq!(separated[*offset], utf16[i]);
+        }
+        assert_eq!(offsets[utf16.len()], separated.len());
+
+        let (separated, inserted) = segmenter.insert_separator(&utf16, &['|' as u16, '|' as u16]);
+        let (stripped, _) = strip_separator(&separated, &['|' as u16, '|' as u16], &inserted);
+        assert_eq!(stripped, utf16);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::segmenter::*;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+/// The encoding of the text read by `StreamSegmenter::read_boundaries`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// Segments text that arrives in chunks, such as a file too large to hold
+/// in memory.
+///
+/// Text is held back until a safe position, where no dictionary word can
+/// span, such as a space, punctuation or a line break. Boundaries before it
+/// are final and reported as offsets from the start of the whole text, the
+/// same as `Segmenter::segment` gives for the whole text at once.
+///
+/// Text without a safe position, such as CJ text without line breaks, is
+/// held back in full until one arrives or `finish` is called, so memory
+/// grows with the longest such stretch.
+pub struct StreamSegmenter<'a> {
+    segmenter: Segmenter<'a>,
+    // The text after the last reported boundary.
+    pending: Vec<u16>,
+    // The offset of pending[0] in the whole text.
+    offset: usize,
+    // Positions in 1..scanned of `pending` are known not to be safe.
+    scanned: usize,
+}
+
+impl<'a> StreamSegmenter<'a> {
+    pub fn new(segmenter: Segmenter<'a>) -> Self {
+        Self {
+            segmenter,
+            pending: Vec::new(),
+            offset: 0,
+            scanned: 0,
+        }
+    }
+
+    /// Appends `chunk` to the text. Returns the boundaries that became
+    /// final. A chunk may end in the middle of a surrogate pair.
+    pub fn push(&mut self, chunk: &[u16]) -> Vec<usize> {
+        self.pending.extend_from_slice(chunk);
+        // Whether the end is safe depends on the next chunk, and so does a
+        // position before a trailing high surrogate, whose low half may still
+        // come. Positions before `scanned` were checked by earlier calls.
+        let mut end = self.pending.len();
+        if matches!(self.pending.last(), Some(0xd800..=0xdbff)) {
+            end -= 1;
+        }
+        let from = self.scanned.max(1);
+        let safe = (from..end)
+            .rev()
+            .find(|pos| self.segmenter.is_safe(&self.pending, *pos));
+        let boundaries = match safe {
+            Some(safe) => self.take(safe),
+            None => Vec::new(),
+        };
+        self.scanned = end - safe.unwrap_or(0);
+        boundaries
+    }
+
+    /// Ends the text and returns the remaining boundaries. The segmenter can
+    /// then take another text.
+    pub fn finish(&mut self) -> Vec<usize> {
+        let boundaries = self.take(self.pending.len());
+        self.offset = 0;
+        self.scanned = 0;
+        boundaries
+    }
+
+    /// Returns an iterator over the boundaries of the text read from
+    /// `reader`, as offsets in code units of `encoding`: bytes for UTF-8 and
+    /// 16-bit units for UTF-16. Invalid UTF-8 and a truncated last code unit
+    /// are `io::ErrorKind::InvalidData` errors.
+    pub fn read_boundaries<R: Read>(self, reader: R, encoding: Encoding) -> ReadBoundaries<'a, R> {
+        ReadBoundaries {
+            stream: self,
+            reader,
+            encoding,
+            bytes: Vec::new(),
+            units: Vec::new(),
+            units_offset: 0,
+            source_offset: 0,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    // Segments pending[..end] and removes it.
+    fn take(&mut self, end: usize) -> Vec<usize> {
+        let offset = self.offset;
+        let boundaries = self
+            .segmenter
+            .segment(&self.pending[..end])
+            .into_iter()
+            .map(|boundary| offset + boundary)
+            .collect();
+        self.pending.drain(..end);
+        self.offset += end;
+        boundaries
+    }
+}
+
+/// Iterator over the boundaries of text from a reader, see
+/// `StreamSegmenter::read_boundaries`.
+pub struct ReadBoundaries<'a, R> {
+    stream: StreamSegmenter<'a>,
+    reader: R,
+    encoding: Encoding,
+    // Bytes of an incomplete character or code unit.
+    bytes: Vec<u8>,
+    // For UTF-8, the text from `units_offset` on, to convert boundaries to
+    // byte offsets. `source_offset` is the byte offset of units[0].
+    units: Vec<u16>,
+    units_offset: usize,
+    source_offset: usize,
+    ready: VecDeque<usize>,
+    done: bool,
+}
+
+impl<'a, R: Read> Iterator for ReadBoundaries<'a, R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = [0; 8192];
+        loop {
+            if let Some(boundary) = self.ready.pop_front() {
+                return Some(Ok(boundary));
+            }
+            if self.done {
+                return None;
+            }
+            let length = match self.reader.read(&mut buffer) {
+                Ok(length) => length,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            if length == 0 {
+                self.done = true;
+                if !self.bytes.is_empty() {
+                    return Some(Err(invalid_data("truncated text")));
+                }
+                let boundaries = self.stream.finish();
+                self.extend(boundaries);
+                continue;
+            }
+            self.bytes.extend_from_slice(&buffer[..length]);
+            match self.decode() {
+                Ok(units) => {
+                    let boundaries = self.stream.push(&units);
+                    if self.encoding == Encoding::Utf8 {
+                        self.units.extend_from_slice(&units);
+                    }
+                    self.extend(boundaries);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, R: Read> ReadBoundaries<'a, R> {
+    // Decodes and removes the complete characters or code units in `bytes`.
+    fn decode(&mut self) -> io::Result<Vec<u16>> {
+        let (units, length) = match self.encoding {
+            Encoding::Utf8 => {
+                let valid = match std::str::from_utf8(&self.bytes) {
+                    Ok(text) => text,
+                    // An incomplete character at the end is kept for the
+                    // next read.
+                    Err(e) if e.error_len().is_none() => {
+                        std::str::from_utf8(&self.bytes[..e.valid_up_to()]).unwrap()
+                    }
+                    Err(_) => return Err(invalid_data("invalid UTF-8")),
+                };
+                (valid.encode_utf16().collect(), valid.len())
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = self
+                    .bytes
+                    .chunks_exact(2)
+                    .map(|pair| match self.encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    })
+                    .collect::<Vec<u16>>();
+                let length = units.len() * 2;
+                (units, length)
+            }
+        };
+        self.bytes.drain(..length);
+        Ok(units)
+    }
+
+    // Queues `boundaries`, converted to offsets in the source encoding.
+    fn extend(&mut self, boundaries: Vec<usize>) {
+        if self.encoding != Encoding::Utf8 {
+            self.ready.extend(boundaries);
+            return;
+        }
+        let mut pos = 0;
+        for boundary in boundaries {
+            for unit in self.units[pos..boundary - self.units_offset].iter() {
+                // Each half of a surrogate pair is two of its four bytes.
+                self.source_offset += match *unit {
+                    0..=0x7f => 1,
+                    0x80..=0x7ff | 0xd800..=0xdfff => 2,
+                    _ => 3,
+                };
+            }
+            pos = boundary - self.units_offset;
+            self.ready.push_back(self.source_offset);
+        }
+        self.units.drain(..pos);
+        self.units_offset += pos;
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::stream::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn stream_test() {
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA)).with_strategy(Strategy::Khmer);
+        let text = "ខ្ញុំស្រឡាញ់អ្នក សួស្តីពិភពលោក។ ខ្ញុំចង់ទៅផ្សារ abc ☺️ ភាសាខ្មែរ";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let expected = segmenter.segment(&utf16);
+        for size in [1, 2, 3, 7, 100] {
+            let mut stream = StreamSegmenter::new(segmenter.clone());
+            let mut boundaries = Vec::new();
+            for chunk in utf16.chunks(size) {
+                boundaries.extend(stream.push(chunk));
+            }
+            boundaries.extend(stream.finish());
+            assert_eq!(boundaries, expected);
+        }
+
+        // Nothing is final before a safe position.
+        let mut stream = StreamSegmenter::new(segmenter.clone());
+        assert_eq!(stream.push(&utf16[..16]), []);
+        // The next push only checks the new units.
+        assert_eq!(stream.scanned, 16);
+        assert_eq!(stream.push(&utf16[16..17]), [5, 12, 16]);
+        assert_eq!((stream.pending.len(), stream.scanned), (1, 1));
+        assert_eq!(stream.finish(), [17]);
+        assert_eq!(stream.push(&utf16[..4]), []);
+        assert_eq!(stream.finish(), [4]);
+
+        // A chunk ends between the halves of a surrogate pair, inside an
+        // emoji with a skin tone modifier.
+        let utf16: Vec<u16> = "ភាសា👍🏻ភាសា".encode_utf16().collect();
+        let expected = segmenter.segment(&utf16);
+        assert_eq!(expected, [4, 8, 12]);
+        for size in [1, 3, 5] {
+            let mut stream = StreamSegmenter::new(segmenter.clone());
+            let mut boundaries = Vec::new();
+            for chunk in utf16.chunks(size) {
+                boundaries.extend(stream.push(chunk));
+            }
+            boundaries.extend(stream.finish());
+            assert_eq!(boundaries, expected);
+        }
+    }
+
+    // A reader that returns one byte per read.
+    struct OneByte<'b>(&'b [u8]);
+
+    impl<'b> Read for OneByte<'b> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let length = self.0.len().min(buffer.len()).min(1);
+            buffer[..length].copy_from_slice(&self.0[..length]);
+            self.0 = &self.0[length..];
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn read_boundaries_test() {
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let text = "焼肉定食を食べる。\n😀 ｺｰﾋｰを飲む\r\n".repeat(1000);
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let expected = segmenter.segment(&utf16);
+
+        let bytes: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+        let stream = StreamSegmenter::new(segmenter.clone());
+        let boundaries: Vec<usize> = stream
+            .read_boundaries(&bytes[..], Encoding::Utf16Be)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(boundaries, expected);
+
+        let stream = StreamSegmenter::new(segmenter.clone());
+        let boundaries: Vec<usize> = stream
+            .read_boundaries(text.as_bytes(), Encoding::Utf8)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let mut utf8_offsets = vec![0; utf16.len() + 1];
+        let mut utf16_offset = 0;
+        for (index, c) in text.char_indices() {
+            utf8_offsets[utf16_offset] = index;
+            utf16_offset += c.len_utf16();
+        }
+        utf8_offsets[utf16.len()] = text.len();
+        let expected: Vec<usize> = expected.iter().map(|b| utf8_offsets[*b]).collect();
+        assert_eq!(boundaries, expected);
+
+        // Reads of one byte split every surrogate pair.
+        let utf16: Vec<u16> = "焼肉👍🏻を食べる".encode_utf16().collect();
+        let bytes: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+        let stream = StreamSegmenter::new(segmenter.clone());
+        let boundaries: Vec<usize> = stream
+            .read_boundaries(OneByte(&bytes), Encoding::Utf16Le)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(boundaries, segmenter.segment(&utf16));
+
+        let stream = StreamSegmenter::new(segmenter.clone());
+        let mut iter = stream.read_boundaries(&b"abc\xff"[..], Encoding::Utf8);
+        assert_eq!(
+            iter.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        let stream = StreamSegmenter::new(segmenter);
+        let result: io::Result<Vec<usize>> = stream
+            .read_boundaries(&b"a\0b"[..], Encoding::Utf16Le)
+            .collect();
+        assert!(result.is_err());
+    }
+}
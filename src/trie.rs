@@ -1,4 +1,7 @@
-#[derive(Clone, Copy, PartialEq)]
+use crate::bytes_trie::*;
+use crate::uchars_trie::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TrieResult {
     // The input unit(s) did not continue a matching string.
     // Once current()/next() return TrieResult::NoMatch,
@@ -22,11 +25,44 @@ pub enum TrieResult {
 pub trait Trie {
     fn first(&mut self, trie_data: &[u8], in_unut: i32) -> TrieResult;
     fn next(&mut self, trie_data: &[u8], in_unut: i32) -> TrieResult;
-    fn box_clone(&self) -> Box<dyn Trie>;
+    fn value(&self, trie_data: &[u8]) -> i32;
+    // Returns all strings of the trie with their values, in sorted order.
+    fn entries(&self, trie_data: &[u8]) -> Vec<(Vec<u16>, i32)>;
+}
+
+// A trie of either type, without boxing.
+#[derive(Clone)]
+pub(crate) enum TrieCursor {
+    Bytes(BytesTrie),
+    UChars(UCharsTrie),
 }
 
-impl Clone for Box<dyn Trie> {
-    fn clone(&self) -> Box<dyn Trie> {
-        self.box_clone()
+impl Trie for TrieCursor {
+    fn first(&mut self, trie_data: &[u8], in_unit: i32) -> TrieResult {
+        match self {
+            TrieCursor::Bytes(trie) => trie.first(trie_data, in_unit),
+            TrieCursor::UChars(trie) => trie.first(trie_data, in_unit),
+        }
+    }
+
+    fn next(&mut self, trie_data: &[u8], in_unit: i32) -> TrieResult {
+        match self {
+            TrieCursor::Bytes(trie) => trie.next(trie_data, in_unit),
+            TrieCursor::UChars(trie) => trie.next(trie_data, in_unit),
+        }
+    }
+
+    fn value(&self, trie_data: &[u8]) -> i32 {
+        match self {
+            TrieCursor::Bytes(trie) => trie.value(trie_data),
+            TrieCursor::UChars(trie) => trie.value(trie_data),
+        }
+    }
+
+    fn entries(&self, trie_data: &[u8]) -> Vec<(Vec<u16>, i32)> {
+        match self {
+            TrieCursor::Bytes(trie) => trie.entries(trie_data),
+            TrieCursor::UChars(trie) => trie.entries(trie_data),
+        }
     }
 }
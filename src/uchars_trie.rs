@@ -17,6 +17,11 @@ const MAX_LINEAR_MATCH_LENGTH: u16 = 0x10;
 const MIN_VALUE_LEAD: u16 = MIN_LINEAR_MATCH + MAX_LINEAR_MATCH_LENGTH; // 0x40
 const NODE_TYPE_MASK: u16 = MIN_VALUE_LEAD - 1; // 0x003f
 
+// Node value: bits 14..6 of a match-node lead unit.
+const MAX_ONE_UNIT_NODE_VALUE: u16 = 0xff;
+const MIN_TWO_UNIT_NODE_VALUE_LEAD: u16 = MIN_VALUE_LEAD + ((MAX_ONE_UNIT_NODE_VALUE + 1) << 6); // 0x4040
+const THREE_UNIT_NODE_VALUE_LEAD: u16 = 0x7fc0;
+
 // A final-value node has bit 15 set.
 const VALUE_IS_FINAL: u16 = 0x8000;
 
@@ -41,6 +46,47 @@ fn skip_value(pos: usize, lead: u16) -> usize {
     }
 }
 
+fn skip_node_value(pos: usize, lead: u16) -> usize {
+    if lead < MIN_TWO_UNIT_NODE_VALUE_LEAD {
+        pos
+    } else if lead < THREE_UNIT_NODE_VALUE_LEAD {
+        pos + 1
+    } else {
+        pos + 2
+    }
+}
+
+fn read_value(uchars: UChars, pos: usize, lead: u16) -> i32 {
+    if lead < MIN_TWO_UNIT_VALUE_LEAD {
+        lead as i32
+    } else if lead < THREE_UNIT_VALUE_LEAD {
+        (((lead - MIN_TWO_UNIT_VALUE_LEAD) as i32) << 16) | uchars.at(pos) as i32
+    } else {
+        (uchars.at(pos) as i32) << 16 | uchars.at(pos + 1) as i32
+    }
+}
+
+fn read_node_value(uchars: UChars, pos: usize, lead: u16) -> i32 {
+    if lead < MIN_TWO_UNIT_NODE_VALUE_LEAD {
+        ((lead >> 6) as i32) - 1
+    } else if lead < THREE_UNIT_NODE_VALUE_LEAD {
+        ((((lead & 0x7fc0) - MIN_TWO_UNIT_NODE_VALUE_LEAD) as i32) << 10) | uchars.at(pos) as i32
+    } else {
+        (uchars.at(pos) as i32) << 16 | uchars.at(pos + 1) as i32
+    }
+}
+
+// The trie data is not guaranteed to be 2-byte aligned, so 16-bit units are
+// read byte-wise instead of reinterpreting the slice.
+#[derive(Clone, Copy)]
+struct UChars<'a>(&'a [u8]);
+
+impl UChars<'_> {
+    fn at(&self, index: usize) -> u16 {
+        u16::from_le_bytes([self.0[index * 2], self.0[index * 2 + 1]])
+    }
+}
+
 #[derive(Clone)]
 pub struct UCharsTrie {
     pos_: Option<usize>,
@@ -52,32 +98,40 @@ impl Trie for UCharsTrie {
     // Traverses the trie from the initial state for this input char.
     // Equivalent to reset() then next(inUnit)
     fn first(&mut self, trie_data: &[u8], c: i32) -> TrieResult {
-        let uchars = unsafe { &*(trie_data as *const [u8] as *const [u16]) };
+        let uchars = UChars(trie_data);
         self.remaining_match_length_ = None;
+        if !(0..=0xffff).contains(&c) {
+            self.stop();
+            return TrieResult::NoMatch;
+        }
         self.next_impl(uchars, self.root_, c as u16)
     }
 
     // Traverses the trie from the current state for this input char.
     fn next(&mut self, trie_data: &[u8], c: i32) -> TrieResult {
-        let uchars = unsafe { &*(trie_data as *const [u8] as *const [u16]) };
+        let uchars = UChars(trie_data);
         if self.pos_.is_none() {
             return TrieResult::NoMatch;
         }
+        if !(0..=0xffff).contains(&c) {
+            self.stop();
+            return TrieResult::NoMatch;
+        }
         let in_byte = c as u16;
         let mut pos = self.pos_.unwrap();
         if let Some(length) = self.remaining_match_length_ {
             // Remaining part of a linear-match node
-            if in_byte == uchars[pos] {
+            if in_byte == uchars.at(pos) {
                 pos += 1;
                 self.pos_ = Some(pos);
                 if length == 0 {
                     self.remaining_match_length_ = None;
-                    let node = uchars[pos];
+                    let node = uchars.at(pos);
                     if node >= MIN_VALUE_LEAD {
                         return Self::value_result(node);
                     }
                 } else {
-                    self.remaining_match_length_ = Some(length);
+                    self.remaining_match_length_ = Some(length - 1);
                 }
                 return TrieResult::NoValue;
             }
@@ -88,12 +142,23 @@ impl Trie for UCharsTrie {
         }
     }
 
-    fn box_clone(&self) -> Box<dyn Trie> {
-        Box::new(UCharsTrie {
-            pos_: self.pos_,
-            root_: self.root_,
-            remaining_match_length_: self.remaining_match_length_,
-        })
+    // Returns the value for the string matched so far.
+    // Only valid after first()/next() returned TrieResult::Intermediate or TrieResult::FinalValue.
+    fn value(&self, trie_data: &[u8]) -> i32 {
+        let uchars = UChars(trie_data);
+        let pos = self.pos_.unwrap();
+        let lead_unit = uchars.at(pos);
+        if lead_unit & VALUE_IS_FINAL != 0 {
+            read_value(uchars, pos + 1, lead_unit & 0x7fff)
+        } else {
+            read_node_value(uchars, pos + 1, lead_unit)
+        }
+    }
+
+    fn entries(&self, trie_data: &[u8]) -> Vec<(Vec<u16>, i32)> {
+        let mut entries = Vec::new();
+        self.collect_node(UChars(trie_data), self.root_, &mut Vec::new(), &mut entries);
+        entries
     }
 }
 
@@ -108,7 +173,7 @@ impl UCharsTrie {
 
     fn branch_next(
         &mut self,
-        uchars: &[u16],
+        uchars: UChars,
         pos: usize,
         length: usize,
         in_unit: u16,
@@ -116,7 +181,7 @@ impl UCharsTrie {
         let mut pos = pos;
         let mut length = length;
         if length == 0 {
-            length = uchars[pos] as usize;
+            length = uchars.at(pos) as usize;
             pos += 1;
         }
         length += 1;
@@ -124,7 +189,7 @@ impl UCharsTrie {
         // The length of the branch is the number of units to select from.
         // The data structure encodes a binary search.
         while length > MAX_BRANCH_LINEAR_SUB_NODE_LENGTH {
-            if in_unit < uchars[pos] {
+            if in_unit < uchars.at(pos) {
                 length >>= 1;
                 pos = self.jump_by_delta(uchars, pos + 1);
             } else {
@@ -136,9 +201,9 @@ impl UCharsTrie {
         // length>=2 because the loop body above sees length>kMaxBranchLinearSubNodeLength>=3
         // and divides length by 2.
         loop {
-            if in_unit == uchars[pos] {
+            if in_unit == uchars.at(pos) {
                 pos += 1;
-                let mut node = uchars[pos];
+                let mut node = uchars.at(pos);
                 if node & VALUE_IS_FINAL != 0 {
                     // Leave the final value for getValue() to read.
                     self.pos_ = Some(pos);
@@ -151,13 +216,13 @@ impl UCharsTrie {
                     pos += node as usize;
                 } else if node < THREE_UNIT_VALUE_LEAD {
                     pos += (((node - MIN_TWO_UNIT_VALUE_LEAD) as u32) << 16) as usize
-                        | uchars[pos] as usize;
+                        | uchars.at(pos) as usize;
                     pos += 1;
                 } else {
-                    pos += (uchars[pos] as usize) << 16 | uchars[pos + 1] as usize;
+                    pos += (uchars.at(pos) as usize) << 16 | uchars.at(pos + 1) as usize;
                     pos += 2;
                 }
-                node = uchars[pos];
+                node = uchars.at(pos);
                 self.pos_ = Some(pos);
 
                 if node >= MIN_VALUE_LEAD {
@@ -172,10 +237,10 @@ impl UCharsTrie {
             }
         }
 
-        if in_unit == uchars[pos] {
+        if in_unit == uchars.at(pos) {
             pos += 1;
             self.pos_ = Some(pos);
-            let node = uchars[pos];
+            let node = uchars.at(pos);
             if node >= MIN_VALUE_LEAD {
                 return Self::value_result(node);
             }
@@ -186,8 +251,8 @@ impl UCharsTrie {
         }
     }
 
-    fn next_impl(&mut self, uchars: &[u16], pos: usize, in_unit: u16) -> TrieResult {
-        let mut node = uchars[pos];
+    fn next_impl(&mut self, uchars: UChars, pos: usize, in_unit: u16) -> TrieResult {
+        let mut node = uchars.at(pos);
         let mut pos = pos + 1;
         loop {
             if node < MIN_LINEAR_MATCH {
@@ -195,12 +260,12 @@ impl UCharsTrie {
             } else if node < MIN_VALUE_LEAD {
                 // Match the first of length+1 units.
                 let length = node - MIN_LINEAR_MATCH;
-                if in_unit == uchars[pos] {
+                if in_unit == uchars.at(pos) {
                     pos += 1;
                     if length == 0 {
                         self.remaining_match_length_ = None;
                         self.pos_ = Some(pos);
-                        node = uchars[pos];
+                        node = uchars.at(pos);
                         if node >= MIN_VALUE_LEAD {
                             return Self::value_result(node);
                         }
@@ -217,7 +282,7 @@ impl UCharsTrie {
                 break;
             } else {
                 // Skip intermediate value.
-                pos = skip_value(pos, node);
+                pos = skip_node_value(pos, node);
                 node &= NODE_TYPE_MASK;
             }
         }
@@ -225,32 +290,109 @@ impl UCharsTrie {
         TrieResult::NoMatch
     }
 
+    fn collect_node(
+        &self,
+        uchars: UChars,
+        pos: usize,
+        prefix: &mut Vec<u16>,
+        entries: &mut Vec<(Vec<u16>, i32)>,
+    ) {
+        let prefix_length = prefix.len();
+        let mut node = uchars.at(pos);
+        let mut pos = pos + 1;
+        loop {
+            if node < MIN_LINEAR_MATCH {
+                let mut length = node as usize;
+                if length == 0 {
+                    length = uchars.at(pos) as usize;
+                    pos += 1;
+                }
+                self.collect_branch(uchars, pos, length + 1, prefix, entries);
+                break;
+            } else if node < MIN_VALUE_LEAD {
+                let length = (node - MIN_LINEAR_MATCH) as usize + 1;
+                prefix.extend((pos..pos + length).map(|i| uchars.at(i)));
+                pos += length;
+                node = uchars.at(pos);
+                pos += 1;
+            } else if node & VALUE_IS_FINAL != 0 {
+                entries.push((prefix.clone(), read_value(uchars, pos, node & 0x7fff)));
+                break;
+            } else {
+                entries.push((prefix.clone(), read_node_value(uchars, pos, node)));
+                pos = skip_node_value(pos, node);
+                node &= NODE_TYPE_MASK;
+            }
+        }
+        prefix.truncate(prefix_length);
+    }
+
+    fn collect_branch(
+        &self,
+        uchars: UChars,
+        pos: usize,
+        length: usize,
+        prefix: &mut Vec<u16>,
+        entries: &mut Vec<(Vec<u16>, i32)>,
+    ) {
+        let mut pos = pos;
+        let mut length = length;
+        while length > MAX_BRANCH_LINEAR_SUB_NODE_LENGTH {
+            // The units less than the split unit are at the jump target.
+            let less_than = self.jump_by_delta(uchars, pos + 1);
+            self.collect_branch(uchars, less_than, length >> 1, prefix, entries);
+            length -= length >> 1;
+            pos = self.skip_delta(uchars, pos + 1);
+        }
+        loop {
+            prefix.push(uchars.at(pos));
+            pos += 1;
+            let node = uchars.at(pos);
+            let value = read_value(uchars, pos + 1, node & 0x7fff);
+            pos = skip_value(pos + 1, node & 0x7fff);
+            if node & VALUE_IS_FINAL != 0 {
+                entries.push((prefix.clone(), value));
+            } else {
+                // The non-final value is the jump delta.
+                self.collect_node(uchars, pos + value as usize, prefix, entries);
+            }
+            prefix.pop();
+            length -= 1;
+            if length <= 1 {
+                break;
+            }
+        }
+        prefix.push(uchars.at(pos));
+        self.collect_node(uchars, pos + 1, prefix, entries);
+        prefix.pop();
+    }
+
     fn stop(&mut self) {
         self.pos_ = None;
     }
 
-    fn jump_by_delta(&self, uchars: &[u16], pos: usize) -> usize {
-        let delta = uchars[pos];
+    fn jump_by_delta(&self, uchars: UChars, pos: usize) -> usize {
+        let delta = uchars.at(pos);
         if delta < MIN_TWO_UNIT_DELTA_LEAD {
             // nothing to do
             pos + 1 + delta as usize
         } else if delta == THREE_UNIT_DELTA_LEAD {
-            let delta = ((uchars[pos + 1] as usize) << 16) | (uchars[pos + 2] as usize);
+            let delta = ((uchars.at(pos + 1) as usize) << 16) | (uchars.at(pos + 2) as usize);
             pos + delta + 3
         } else {
             let delta =
-                ((delta - MIN_TWO_UNIT_DELTA_LEAD) as usize) << 16 | (uchars[pos + 1] as usize);
+                ((delta - MIN_TWO_UNIT_DELTA_LEAD) as usize) << 16 | (uchars.at(pos + 1) as usize);
             pos + delta + 2
         }
     }
 
-    fn skip_value(&self, uchars: &[u16], pos: usize) -> usize {
-        let lead_byte = uchars[pos];
+    fn skip_value(&self, uchars: UChars, pos: usize) -> usize {
+        let lead_byte = uchars.at(pos);
         skip_value(pos + 1, lead_byte & 0x7fff)
     }
 
-    fn skip_delta(&self, uchars: &[u16], pos: usize) -> usize {
-        let delta = uchars[pos];
+    fn skip_delta(&self, uchars: UChars, pos: usize) -> usize {
+        let delta = uchars.at(pos);
         if delta < MIN_TWO_UNIT_DELTA_LEAD {
             pos + 1
         } else if delta == THREE_UNIT_DELTA_LEAD {
@@ -0,0 +1,199 @@
+use crate::segmenter::*;
+
+/// Random access to the word boundaries of a text, in the manner of ICU's
+/// `BreakIterator`.
+///
+/// Boundaries are computed lazily between safe positions, where no
+/// dictionary word can span, and cached. A query outside the cached range
+/// re-synchronizes from the nearest safe position before it instead of
+/// segmenting from the start of the text.
+///
+/// Iterating yields the end offset of each segment, like
+/// `Segmenter::segment`, from either end of the text.
+pub struct WordBoundaries<'s, 'a> {
+    segmenter: &'s Segmenter<'a>,
+    input: &'s [u16],
+    // All boundaries from cache[0] to the last element, both safe positions.
+    cache: Vec<usize>,
+    front_offset: usize,
+    back_offset: usize,
+    scratch: Scratch,
+}
+
+impl<'s, 'a> Iterator for WordBoundaries<'s, 'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front_offset >= self.back_offset {
+            return None;
+        }
+        let boundary = self.following(self.front_offset)?;
+        self.front_offset = boundary;
+        Some(boundary)
+    }
+}
+
+impl<'s, 'a> DoubleEndedIterator for WordBoundaries<'s, 'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front_offset >= self.back_offset {
+            return None;
+        }
+        let boundary = self.back_offset;
+        self.back_offset = self.preceding(boundary).unwrap_or(0);
+        Some(boundary)
+    }
+}
+
+impl<'s, 'a> WordBoundaries<'s, 'a> {
+    pub(crate) fn new(segmenter: &'s Segmenter<'a>, input: &'s [u16]) -> Self {
+        Self {
+            segmenter,
+            input,
+            cache: Vec::new(),
+            front_offset: 0,
+            back_offset: input.len(),
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// Returns whether `offset` is a word boundary. The start and the end of
+    /// the text are always boundaries.
+    pub fn is_boundary(&mut self, offset: usize) -> bool {
+        if offset == 0 || offset == self.input.len() {
+            return true;
+        }
+        if offset > self.input.len() {
+            return false;
+        }
+        self.fill(offset);
+        self.cache.binary_search(&offset).is_ok()
+    }
+
+    /// Returns the first boundary after `offset`.
+    pub fn following(&mut self, offset: usize) -> Option<usize> {
+        if offset >= self.input.len() {
+            return None;
+        }
+        self.fill(offset + 1);
+        let index = match self.cache.binary_search(&offset) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+        Some(self.cache[index])
+    }
+
+    /// Returns the last boundary before `offset`.
+    pub fn preceding(&mut self, offset: usize) -> Option<usize> {
+        if offset == 0 {
+            return None;
+        }
+        let offset = offset.min(self.input.len() + 1);
+        self.fill(offset - 1);
+        let index = match self.cache.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index,
+        };
+        Some(self.cache[index - 1])
+    }
+
+    // Makes the cache cover `offset`.
+    fn fill(&mut self, offset: usize) {
+        let offset = offset.min(self.input.len());
+        if let (Some(first), Some(last)) = (self.cache.first(), self.cache.last()) {
+            if *first <= offset && offset <= *last {
+                return;
+            }
+        }
+        let start = self.segmenter.safe_position_before(self.input, offset);
+        let extend = match (self.cache.first(), self.cache.last()) {
+            (Some(first), Some(last)) => *first <= start && start <= *last,
+            _ => false,
+        };
+        if !extend {
+            self.cache.clear();
+            self.cache.push(start);
+        }
+        while *self.cache.last().unwrap() < offset {
+            let from = *self.cache.last().unwrap();
+            let to = self.segmenter.safe_position_after(self.input, from + 1);
+            self.segmenter
+                .segment_range(self.input, from, to, &mut self.cache, &mut self.scratch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::*;
+    use crate::segmenter::*;
+    const KHMER_DATA: &[u8; 445542] = include_bytes!("../data/khmerdict.dict");
+    const CJ_DATA: &[u8; 2003566] = include_bytes!("../data/cjdict.dict");
+
+    #[test]
+    fn random_access_test() {
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ ab ភាសាខ្មែរ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        let mut boundaries = segmenter.word_boundaries(&utf16);
+
+        // Start in the middle; only the last Khmer run has to be segmented.
+        assert!(boundaries.is_boundary(31));
+        assert!(!boundaries.is_boundary(25));
+        assert_eq!(boundaries.cache, [22, 31]);
+
+        assert!(boundaries.is_boundary(9));
+        assert!(!boundaries.is_boundary(10));
+        assert_eq!(boundaries.following(0), Some(9));
+        assert_eq!(boundaries.following(9), Some(18));
+        assert_eq!(boundaries.following(10), Some(18));
+        assert_eq!(boundaries.following(18), Some(19));
+        assert_eq!(boundaries.following(30), Some(31));
+        assert_eq!(boundaries.following(31), None);
+        assert_eq!(boundaries.preceding(31), Some(22));
+        assert_eq!(boundaries.preceding(22), Some(21));
+        assert_eq!(boundaries.preceding(17), Some(9));
+        assert_eq!(boundaries.preceding(9), Some(0));
+        assert_eq!(boundaries.preceding(0), None);
+    }
+
+    #[test]
+    fn reverse_test() {
+        let utf16: Vec<u16> = "ភាសាខ្មែរភាសាខ្មែរ ab ភាសាខ្មែរ".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(KHMER_DATA));
+        let forward: Vec<usize> = segmenter.word_boundaries(&utf16).collect();
+        assert_eq!(forward, segmenter.segment(&utf16));
+        let mut backward: Vec<usize> = segmenter.word_boundaries(&utf16).rev().collect();
+        backward.reverse();
+        assert_eq!(backward, forward);
+
+        let utf16: Vec<u16> = "焼肉定食を食べる".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let mut boundaries = segmenter.word_boundaries(&utf16);
+        assert_eq!(boundaries.next_back(), Some(8));
+        assert_eq!(boundaries.next(), Some(2));
+        assert_eq!(boundaries.next_back(), Some(5));
+        assert_eq!(boundaries.next_back(), Some(4));
+        assert_eq!(boundaries.next(), None);
+        assert_eq!(boundaries.next_back(), None);
+    }
+
+    #[test]
+    fn matches_segment_test() {
+        let utf16: Vec<u16> = "焼肉定食を食べる".encode_utf16().collect();
+        let segmenter = Segmenter::new(Dictionary::new(CJ_DATA));
+        let expected = segmenter.segment(&utf16);
+        let mut boundaries = segmenter.word_boundaries(&utf16);
+        let mut offset = 0;
+        let mut actual = Vec::new();
+        while let Some(next) = boundaries.following(offset) {
+            actual.push(next);
+            offset = next;
+        }
+        assert_eq!(actual, expected);
+        for offset in 0..=utf16.len() {
+            assert_eq!(
+                boundaries.is_boundary(offset),
+                offset == 0 || expected.contains(&offset)
+            );
+        }
+    }
+}